@@ -0,0 +1,168 @@
+//! describe一个大的parquet文件, 对比逐个统计量union(旧实现)和单次aggregate的扫描次数与耗时,
+//! 扫描次数算的是默认describe(统计量和categorical)执行的所有查询
+//!
+//! cargo bench --bench describe
+
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use arrow::{
+    array::{
+        ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray, TimestampMicrosecondArray,
+    },
+    datatypes::{DataType, Field, Schema, TimeUnit},
+};
+use criterion::{criterion_group, criterion_main, Criterion};
+use datafusion::{
+    physical_plan::ExecutionPlan,
+    prelude::{cast, col, lit, DataFrame, ParquetReadOptions, SessionContext},
+};
+use parquet::arrow::ArrowWriter;
+use taotie::backend::fusion::{
+    describe2::{DataFrameDescriber, DescribeMethod},
+    stats::RecordingPlanner,
+};
+use tokio::runtime::Runtime;
+
+const ROWS: usize = 2_000_000;
+const BATCH_SIZE: usize = 100_000;
+
+fn generate_parquet() -> anyhow::Result<PathBuf> {
+    let path = std::env::temp_dir().join(format!("taotie_describe_{}.parquet", ROWS));
+    if path.exists() {
+        return Ok(path);
+    }
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Int64, false),
+        Field::new("score", DataType::Float64, true),
+        Field::new("name", DataType::Utf8, true),
+        Field::new(
+            "created_at",
+            DataType::Timestamp(TimeUnit::Microsecond, None),
+            true,
+        ),
+    ]));
+    let mut writer = ArrowWriter::try_new(File::create(&path)?, schema.clone(), None)?;
+    for start in (0..ROWS).step_by(BATCH_SIZE) {
+        let ids = (start..start + BATCH_SIZE).map(|i| i as i64);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(Int64Array::from_iter_values(ids.clone())),
+            Arc::new(Float64Array::from_iter(
+                ids.clone()
+                    .map(|i| (i % 7 != 0).then(|| (i * 37 % 1000) as f64 / 10.0)),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                ids.clone().map(|i| "x".repeat((i % 20) as usize)),
+            )),
+            Arc::new(TimestampMicrosecondArray::from_iter_values(
+                ids.map(|i| 1_600_000_000_000_000 + i * 1_000_000),
+            )),
+        ];
+        writer.write(&RecordBatch::try_new(schema.clone(), columns)?)?;
+    }
+    writer.close()?;
+    Ok(path)
+}
+
+/// the previous implementation: one aggregate per statistic, then union them
+fn union_describe(df: DataFrame) -> anyhow::Result<DataFrame> {
+    let names: Vec<String> = df
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| f.name().to_string())
+        .collect();
+    let mut acc: Option<DataFrame> = None;
+    for method in DescribeMethod::defaults() {
+        let stat_df = DataFrameDescriber::try_new(df.clone())?
+            .with_methods(vec![method])
+            .stats_df()?;
+        // the first column is the shared row count, the rest is one value per column
+        let mut select_expr = vec![lit(method.to_string()).alias("describe")];
+        select_expr.extend(
+            stat_df
+                .schema()
+                .fields()
+                .iter()
+                .skip(1)
+                .zip(names.iter())
+                .map(|(f, name)| {
+                    cast(col(format!("\"{}\"", f.name())), DataType::Float64).alias(name)
+                }),
+        );
+        let stat_df = stat_df.select(select_expr)?;
+        acc = Some(match acc {
+            Some(acc) => acc.union(stat_df)?,
+            None => stat_df,
+        });
+    }
+    Ok(acc.expect("at least one statistic"))
+}
+
+/// number of parquet scans in a physical plan
+fn count_scans(plan: &Arc<dyn ExecutionPlan>) -> usize {
+    let own = usize::from(plan.name() == "ParquetExec");
+    own + plan.children().into_iter().map(count_scans).sum::<usize>()
+}
+
+async fn read(path: &Path) -> DataFrame {
+    let ctx = SessionContext::new();
+    ctx.read_parquet(path.to_str().unwrap(), ParquetReadOptions::default())
+        .await
+        .unwrap()
+}
+
+/// parquet scans of every query the default `describe` runs, recorded by the planner
+async fn describe_scans(path: &Path) -> usize {
+    let planner = Arc::new(RecordingPlanner::default());
+    planner.set_enabled(true);
+    let state = SessionContext::new()
+        .state()
+        .with_query_planner(planner.clone());
+    let df = SessionContext::new_with_state(state)
+        .read_parquet(path.to_str().unwrap(), ParquetReadOptions::default())
+        .await
+        .unwrap();
+    let describer = DataFrameDescriber::try_new(df).unwrap();
+    describer.report(0, false).await.unwrap();
+    let (plans, _) = planner.take();
+    plans.iter().map(|(plan, _)| count_scans(plan)).sum()
+}
+
+fn describe_benchmark(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let path = generate_parquet().unwrap();
+
+    rt.block_on(async {
+        let union = union_describe(read(&path).await).unwrap();
+        let union_scans = count_scans(&union.create_physical_plan().await.unwrap());
+        let single_scans = describe_scans(&path).await;
+        println!(
+            "parquet scans: union describe = {union_scans}, single pass describe = {single_scans}"
+        );
+        // statistics and the categorical section of the string column share the scan
+        assert_eq!(single_scans, 1);
+    });
+
+    let mut group = c.benchmark_group("describe");
+    group.sample_size(10);
+    group.bench_function("union", |b| {
+        b.to_async(&rt).iter(|| async {
+            let df = union_describe(read(&path).await).unwrap();
+            df.collect().await.unwrap()
+        })
+    });
+    group.bench_function("single_pass", |b| {
+        b.to_async(&rt).iter(|| async {
+            let describer = DataFrameDescriber::try_new(read(&path).await).unwrap();
+            describer.report(0, false).await.unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, describe_benchmark);
+criterion_main!(benches);
@@ -1,17 +1,39 @@
 //! 自己实现describe
 //! 接受一个dataframe, 将其转换为一个新的dataframe
 //!
-//! 新的dataframe 将会将string转为len(string), 将date转为bigint
+//! 新的dataframe 将会将string转为len(string), 将date转为bigint, bool转为0/1
+//! list转为array_length(list), 结果里标记为length(col), 元素的统计量单独展开(unnest)后计算
+//!
+//! 所有统计量在一个aggregate里计算(只扫描一次数据), 再把这一行结果转置成describe的格式
 
-use std::{fmt::Display, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    str::FromStr,
+    sync::Arc,
+};
 
+use super::flatten::is_nested;
+use super::moments::{kurtosis, skew};
 use anyhow::Result;
-use arrow::datatypes::{DataType, Field};
+use arrow::{
+    array::{Array, ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray},
+    compute::cast as cast_array,
+    datatypes::{DataType, Field, FieldRef, Schema},
+    util::display::array_value_to_string,
+};
 use datafusion::{
     functions_aggregate::{
-        approx_percentile_cont, count::count, expr_fn::avg, median::median, stddev::stddev,
+        approx_distinct::approx_distinct,
+        approx_percentile_cont,
+        count::{count, count_distinct},
+        expr_fn::avg,
+        median::median,
+        stddev::stddev,
+        sum::sum,
+        variance::var_sample,
     },
-    prelude::{array_length, case, cast, col, length, lit, max, min, DataFrame},
+    prelude::{array_length, cast, col, ident, length, lit, max, min, when, DataFrame, Expr},
 };
 
 #[derive(Debug)]
@@ -19,223 +41,1097 @@ pub struct DataFrameDescriber {
     original: DataFrame,
     transformed: DataFrame,
     methods: Vec<DescribeMethod>,
+    by: Vec<String>,
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DescribeMethod {
     Count,
     NullCount,
+    DistinctCount,
     Mean,
     Std,
+    Variance,
     Min,
     Max,
+    Sum,
     Median,
+    Mode,
+    Skew,
+    Kurtosis,
     Percentile(u8),
 }
 
+impl DescribeMethod {
+    /// the statistics shown when `describe` is called without `--stats`
+    pub fn defaults() -> Vec<DescribeMethod> {
+        vec![
+            DescribeMethod::Count,
+            DescribeMethod::NullCount,
+            DescribeMethod::Mean,
+            DescribeMethod::Std,
+            DescribeMethod::Min,
+            DescribeMethod::Max,
+            DescribeMethod::Median,
+            DescribeMethod::Percentile(25),
+            DescribeMethod::Percentile(50),
+            DescribeMethod::Percentile(75),
+        ]
+    }
+}
+
+impl DescribeMethod {
+    /// whether the statistic is a value of the column, e.g. min is a date for a date column
+    pub fn in_value_domain(&self) -> bool {
+        matches!(
+            self,
+            DescribeMethod::Mean
+                | DescribeMethod::Min
+                | DescribeMethod::Max
+                | DescribeMethod::Median
+                | DescribeMethod::Mode
+                | DescribeMethod::Percentile(_)
+        )
+    }
+}
+
+impl FromStr for DescribeMethod {
+    type Err = String;
+
+    /// accept the display name, `pN` is a short form of `percentile_N`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let method = match s.as_str() {
+            "count" => DescribeMethod::Count,
+            "null_count" => DescribeMethod::NullCount,
+            "distinct_count" => DescribeMethod::DistinctCount,
+            "mean" | "avg" => DescribeMethod::Mean,
+            "std" => DescribeMethod::Std,
+            "variance" | "var" => DescribeMethod::Variance,
+            "min" => DescribeMethod::Min,
+            "max" => DescribeMethod::Max,
+            "sum" => DescribeMethod::Sum,
+            "median" => DescribeMethod::Median,
+            "mode" => DescribeMethod::Mode,
+            "skew" => DescribeMethod::Skew,
+            "kurtosis" => DescribeMethod::Kurtosis,
+            s => {
+                let p = s
+                    .strip_prefix("percentile_")
+                    .or_else(|| s.strip_prefix('p'))
+                    .ok_or_else(|| format!("Unknown statistic: {}", s))?;
+                DescribeMethod::Percentile(parse_percentile(p)?)
+            }
+        };
+        Ok(method)
+    }
+}
+
+/// parse a percentile between 0 and 100
+pub fn parse_percentile(s: &str) -> std::result::Result<u8, String> {
+    match s.trim().parse::<u8>() {
+        Ok(p) if p <= 100 => Ok(p),
+        _ => Err(format!("Invalid percentile: {}, expect 0-100", s)),
+    }
+}
+
 impl Display for DescribeMethod {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             DescribeMethod::Count => write!(f, "count"),
             DescribeMethod::NullCount => write!(f, "null_count"),
+            DescribeMethod::DistinctCount => write!(f, "distinct_count"),
             DescribeMethod::Mean => write!(f, "mean"),
             DescribeMethod::Std => write!(f, "std"),
+            DescribeMethod::Variance => write!(f, "variance"),
             DescribeMethod::Min => write!(f, "min"),
             DescribeMethod::Max => write!(f, "max"),
+            DescribeMethod::Sum => write!(f, "sum"),
             DescribeMethod::Median => write!(f, "median"),
+            DescribeMethod::Mode => write!(f, "mode"),
+            DescribeMethod::Skew => write!(f, "skew"),
+            DescribeMethod::Kurtosis => write!(f, "kurtosis"),
             DescribeMethod::Percentile(p) => write!(f, "percentile_{}", p),
         }
     }
 }
-macro_rules! impl_describe_method {
-    ($method:ident) => {
-        fn $method(&self) -> Result<DataFrame> {
-            let fields = self.transformed.schema().fields().iter();
-            let expr = fields
-                .map(|field| $method(col(field.name())).alias(field.name()))
-                .collect();
-            let df = self.transformed.clone().aggregate(vec![], expr);
-            Ok(df?)
-        }
-    };
-}
 impl DataFrameDescriber {
+    /// struct columns can't be described, they are skipped unless flattened before
     pub fn try_new(df: DataFrame) -> Result<Self> {
         let fields = df.schema().fields().iter();
         let expr = fields
-            .map(|field| {
-                let dt = field.data_type();
-                let expr = match dt {
-                    dt if dt.is_temporal() => cast(col(field.name()), DataType::Float64),
-                    dt if dt.is_numeric() => col(field.name()),
-                    DataType::List(_) => array_length(col(field.name())),
-                    _ => length(col(field.name())),
-                };
-                expr.alias(field.name())
-            })
+            .filter(|field| !is_nested(field.data_type()))
+            .flat_map(|field| described(field))
             .collect();
 
         let transformed = df.clone().select(expr)?;
         Ok(Self {
             original: df,
             transformed,
-            methods: vec![
-                DescribeMethod::Count,
-                DescribeMethod::NullCount,
-                DescribeMethod::Mean,
-                DescribeMethod::Std,
-                DescribeMethod::Min,
-                DescribeMethod::Max,
-                DescribeMethod::Median,
-                DescribeMethod::Percentile(25),
-                DescribeMethod::Percentile(50),
-                DescribeMethod::Percentile(75),
-            ],
+            methods: DescribeMethod::defaults(),
+            by: vec![],
         })
     }
 
-    // fn count(&self) -> Result<DataFrame> {
-    //     let fields = self.transformed.schema().fields().iter();
-    //     let expr = fields.map(|field| {
-    //         count(col(field.name())).alias(field.name())
-    //     }).collect();
-    //     let df = self.transformed.clone().aggregate(vec![], expr);
-    //     Ok(df?)
-    // }
+    /// replace the default statistics, duplicated methods are only computed once
+    pub fn with_methods(mut self, methods: Vec<DescribeMethod>) -> Self {
+        let mut deduped: Vec<DescribeMethod> = Vec::with_capacity(methods.len());
+        for method in methods {
+            if !deduped.contains(&method) {
+                deduped.push(method);
+            }
+        }
+        self.methods = deduped;
+        self
+    }
 
-    fn null_count(&self) -> Result<DataFrame> {
-        let fields = self.transformed.schema().fields().iter();
-        let expr = fields
-            .map(|field| {
-                count(
-                    case(col(field.name()).is_null())
-                        .when(lit(true), lit(1))
-                        .otherwise(lit(0))
-                        .unwrap(),
-                )
-                .alias(field.name())
+    /// compute the statistics per group, the group columns keep their original values
+    pub fn with_group_by(mut self, by: Vec<String>) -> Result<Self> {
+        let schema = self.original.schema();
+        for name in by.iter() {
+            schema.field_with_unqualified_name(name)?;
+        }
+        let expr = schema
+            .fields()
+            .iter()
+            .filter(|field| !is_nested(field.data_type()))
+            .flat_map(|field| match by.contains(field.name()) {
+                // binary group values are shown as text instead of hex
+                true if matches!(field.data_type(), DataType::Binary | DataType::LargeBinary) => {
+                    vec![cast(ident(field.name()), DataType::Utf8).alias(field.name())]
+                }
+                true => vec![ident(field.name())],
+                false => described(field),
             })
             .collect();
-        let df = self.transformed.clone().aggregate(vec![], expr);
-        Ok(df?)
-    }
-
-    // fn mean(&self) -> Result<DataFrame> {
-    //     let fields = self.transformed.schema().fields().iter();
-    //     let expr = fields.map(|field| {
-    //             avg(col(field.name())).alias(field.name())
-    //     }).collect();
-    //     let df = self.transformed.clone().aggregate(vec![], expr);
-    //     Ok(df?)
-
-    // }
-
-    // fn std(&self) -> Result<DataFrame> {
-    //     let fields = self.transformed.schema().fields().iter();
-    //     let expr = fields.map(|field|{
-    //         stddev(col(field.name())).alias(field.name())
-    //     }).collect();
-    //     let df = self.transformed.clone().aggregate(vec![], expr);
-    //     Ok(df?)
-    // }
-
-    // fn min(&self) -> Result<DataFrame> {
-    //     let fields = self.transformed.schema().fields().iter();
-    //     let expr = fields.map(|field|{
-    //         min(col(field.name())).alias(field.name())
-    //     }).collect();
-    //     let df = self.transformed.clone().aggregate(vec![], expr);
-    //     Ok(df?)
-
-    // }
-
-    // fn max(&self) -> Result<DataFrame>{
-    //     let fields = self.transformed.schema().fields().iter();
-    //     let expr = fields.map(|field|{
-    //         max(col(field.name())).alias(field.name())
-    //     }).collect();
-    //     let df = self.transformed.clone().aggregate(vec![], expr);
-    //     Ok(df?)
-    // }
-
-    // fn median(&self) -> Result<DataFrame> {
-    //     let fields = self.transformed.schema().fields().iter();
-    //     let expr = fields.map(|field|{
-    //         median(col(field.name())).alias(field.name())
-    //     }).collect();
-    //     let df = self.transformed.clone().aggregate(vec![], expr);
-    //     Ok(df?)
-    // }
-
-    fn percentile(&self, p: u8) -> Result<DataFrame> {
-        let fields = self.transformed.schema().fields().iter();
+        self.transformed = self.original.clone().select(expr)?;
+        self.by = by;
+        Ok(self)
+    }
+
+    /// the columns to describe, group columns and the text values of string columns
+    /// are excluded
+    fn stat_fields(&self) -> Vec<FieldRef> {
+        let original = self.original.schema();
+        self.transformed
+            .schema()
+            .fields()
+            .iter()
+            .filter(|f| !self.by.contains(f.name()))
+            .filter(|f| original.has_column_with_unqualified_name(f.name()))
+            .cloned()
+            .collect()
+    }
+
+    /// the column holding the values of `name`, which is not the described length
+    /// for string columns
+    fn values_column(&self, name: &str) -> String {
+        let values = values_name(name);
+        match self
+            .transformed
+            .schema()
+            .has_column_with_unqualified_name(&values)
+        {
+            true => values,
+            false => name.to_string(),
+        }
+    }
+
+    /// a single aggregate computing every statistic of every column,
+    /// each value is aliased as `{column}__{stat}`
+    pub fn stats_df(&self) -> Result<DataFrame> {
+        self.stats_aggregate(false)
+    }
+
+    /// the stats aggregate, with the values of the categorical section in the same pass
+    /// when `categorical`
+    fn stats_aggregate(&self, categorical: bool) -> Result<DataFrame> {
+        let mut seen = HashSet::new();
+        let mut expr = vec![count(lit(1)).alias(ROWS)];
+        let mut push = |name: &str, key: &str, e: Expr| {
+            let alias = stat_name(name, key);
+            if seen.insert(alias.clone()) {
+                expr.push(e.alias(alias));
+            }
+        };
+        for field in self.stat_fields() {
+            let values = self.values_column(field.name());
+            for method in self.methods.iter() {
+                for (key, e) in stat_exprs(method, field.name(), &values) {
+                    push(field.name(), &key, e);
+                }
+            }
+            if categorical && is_categorical(self.original_type(field.name())?) {
+                for (key, e) in categorical_exprs(&values) {
+                    push(field.name(), key, e);
+                }
+            }
+        }
+        let group_expr = self.by.iter().map(ident).collect();
+        let df = self.transformed.clone().aggregate(group_expr, expr)?;
+        Ok(df)
+    }
+
+    fn original_type(&self, name: &str) -> Result<&DataType> {
+        let field = self.original.schema().field_with_unqualified_name(name)?;
+        Ok(field.data_type())
+    }
+
+    /// the most frequent value of each column per group, it can't be computed by a plain
+    /// aggregate, so every column needs an extra group by
+    async fn modes(&self) -> Result<HashMap<GroupKey, Vec<Option<ModeValue>>>> {
+        let fields = self.stat_fields();
+        let mut modes: HashMap<GroupKey, Vec<Option<ModeValue>>> = HashMap::new();
+        for (i, field) in fields.iter().enumerate() {
+            let name = self.values_column(field.name());
+            let text = name != *field.name();
+            let mut group_expr: Vec<Expr> = self.by.iter().map(ident).collect();
+            group_expr.push(ident(&name));
+            let batches = self
+                .transformed
+                .clone()
+                .filter(ident(&name).is_not_null())?
+                .aggregate(group_expr, vec![count(lit(1)).alias("__freq")])?
+                .collect()
+                .await?;
+            // (freq, value) of the most frequent value, the smaller value wins on ties
+            let mut best: HashMap<GroupKey, (f64, ModeValue)> = HashMap::new();
+            for batch in batches.iter() {
+                let n = self.by.len();
+                for r in 0..batch.num_rows() {
+                    let value = match text {
+                        true => batch
+                            .column(n)
+                            .is_valid(r)
+                            .then(|| array_value_to_string(batch.column(n), r))
+                            .transpose()?
+                            .map(ModeValue::Text),
+                        false => float_value(batch.column(n), r)?.map(ModeValue::Number),
+                    };
+                    let (Some(value), Some(freq)) = (value, float_value(batch.column(n + 1), r)?)
+                    else {
+                        continue;
+                    };
+                    let entry = best
+                        .entry(group_key(batch, r, n)?)
+                        .or_insert((freq, value.clone()));
+                    if freq > entry.0 || (freq == entry.0 && value < entry.1) {
+                        *entry = (freq, value);
+                    }
+                }
+            }
+            for (key, (_, value)) in best {
+                modes.entry(key).or_insert_with(|| vec![None; fields.len()])[i] = Some(value);
+            }
+        }
+        Ok(modes)
+    }
+
+    /// values of every statistic for row `r` of the stats aggregate, indexed by [column][method]
+    fn stat_values(
+        &self,
+        row: &RecordBatch,
+        r: usize,
+        modes: Option<&Vec<Option<ModeValue>>>,
+    ) -> Result<Vec<Vec<Option<f64>>>> {
+        let get = |name: &str, key: &str| -> Result<Option<f64>> {
+            match row.column_by_name(&stat_name(name, key)) {
+                Some(array) => float_value(array, r),
+                None => Ok(None),
+            }
+        };
+        let rows = float_value(
+            row.column_by_name(ROWS).expect("rows is always computed"),
+            r,
+        )?;
+
+        let mut columns = vec![];
+        for (i, field) in self.stat_fields().iter().enumerate() {
+            let name = field.name();
+            let mut values = Vec::with_capacity(self.methods.len());
+            for method in self.methods.iter() {
+                let value = match method {
+                    DescribeMethod::Mode => match modes.and_then(|m| m[i].as_ref()) {
+                        Some(ModeValue::Number(v)) => Some(*v),
+                        _ => None,
+                    },
+                    DescribeMethod::NullCount => {
+                        rows.zip(get(name, "count")?).map(|(rows, n)| rows - n)
+                    }
+                    method => get(name, &stat_key(method))?,
+                };
+                values.push(value);
+            }
+            columns.push(values);
+        }
+        Ok(columns)
+    }
+
+    async fn collect_stats(
+        &self,
+        categorical: bool,
+    ) -> Result<(Vec<RecordBatch>, HashMap<GroupKey, Vec<Option<ModeValue>>>)> {
+        if self.methods.is_empty() {
+            anyhow::bail!("No describe found");
+        }
+        // groups are ordered by their values, not by how they are rendered
+        let order = self.by.iter().map(|b| ident(b).sort(true, false)).collect();
+        let batches = self
+            .stats_aggregate(categorical)?
+            .sort(order)?
+            .collect()
+            .await?;
+        let modes = if self.methods.contains(&DescribeMethod::Mode) {
+            self.modes().await?
+        } else {
+            HashMap::new()
+        };
+        Ok((batches, modes))
+    }
+
+    /// run the stats aggregate once and pivot the single wide row:
+    /// one row per statistic, one column per original column
+    pub async fn describe(&self) -> Result<RecordBatch> {
+        let (batches, modes) = self.collect_stats(false).await?;
+        self.pivot(first_row(&batches)?, &modes)
+    }
+
+    /// the statistics with temporal values cast back, the categorical section of string
+    /// columns from the same scan, and the element section of list columns when `lists`,
+    /// which takes a scan per list column; the `top_k` values take one per string column
+    pub async fn report(&self, top_k: usize, lists: bool) -> Result<DescribeReport> {
+        let (batches, modes) = self.collect_stats(true).await?;
+        let row = first_row(&batches)?;
+        let stats = self.cast_back(self.pivot(row, &modes)?)?;
+        let categorical = self.categorical(row, top_k).await?;
+        let lists = match lists {
+            true => self.lists().await?,
+            false => None,
+        };
+        Ok(DescribeReport {
+            stats,
+            categorical,
+            lists,
+            sparklines: None,
+        })
+    }
+
+    fn pivot(
+        &self,
+        row: &RecordBatch,
+        modes: &HashMap<GroupKey, Vec<Option<ModeValue>>>,
+    ) -> Result<RecordBatch> {
+        let values = self.stat_values(row, 0, modes.get(&vec![]))?;
+
+        let mut fields = vec![Field::new("describe", DataType::Utf8, false)];
+        let mut columns: Vec<ArrayRef> = vec![Arc::new(StringArray::from_iter_values(
+            self.methods.iter().map(|m| m.to_string()),
+        ))];
+        let mode = self.methods.iter().position(|m| *m == DescribeMethod::Mode);
+        for (i, (field, values)) in self.stat_fields().iter().zip(values).enumerate() {
+            let label = self.label(field.name())?;
+            // the mode of a string column is a value, so the whole column is shown as text
+            match mode {
+                Some(j) if self.values_column(field.name()) != *field.name() => {
+                    let mut rendered = render_values(&self.methods, values, &DataType::Float64)?;
+                    rendered[j] = text_mode(modes.get(&vec![]), i);
+                    fields.push(Field::new(label, DataType::Utf8, true));
+                    columns.push(Arc::new(StringArray::from(rendered)));
+                }
+                _ => {
+                    fields.push(Field::new(label, DataType::Float64, true));
+                    columns.push(Arc::new(Float64Array::from(values)));
+                }
+            }
+        }
+
+        let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)?;
+        Ok(batch)
+    }
+
+    /// statistics per group in long format: group columns, column, describe, value
+    pub async fn describe_by(&self) -> Result<RecordBatch> {
+        let (batches, modes) = self.collect_stats(false).await?;
+        let n = self.by.len();
+        let mut groups = vec![];
+        for batch in batches.iter() {
+            for r in 0..batch.num_rows() {
+                let by_values = group_key(batch, r, n)?;
+                let modes = modes.get(&by_values);
+                let values = self.stat_values(batch, r, modes)?;
+                let text_modes = (0..self.stat_fields().len())
+                    .map(|i| text_mode(modes, i))
+                    .collect::<Vec<_>>();
+                groups.push((by_values, values, text_modes));
+            }
+        }
+        let mode = self.methods.iter().position(|m| *m == DescribeMethod::Mode);
+
+        let stat_fields = self.stat_fields();
+        let mut by_columns: Vec<Vec<Option<String>>> = vec![vec![]; n];
+        let (mut names, mut stats, mut rendered) = (vec![], vec![], vec![]);
+        for (by_values, values, text_modes) in groups {
+            for ((field, values), text) in stat_fields.iter().zip(values).zip(text_modes) {
+                let original = self
+                    .original
+                    .schema()
+                    .field_with_unqualified_name(field.name())?;
+                let mut rendered_values =
+                    render_values(&self.methods, values, original.data_type())?;
+                if let (Some(j), Some(text)) = (mode, text) {
+                    rendered_values[j] = Some(text);
+                }
+                for (method, value) in self.methods.iter().zip(rendered_values) {
+                    for (i, v) in by_values.iter().enumerate() {
+                        by_columns[i].push(v.clone());
+                    }
+                    names.push(self.label(field.name())?);
+                    stats.push(method.to_string());
+                    rendered.push(value);
+                }
+            }
+        }
+
+        let mut fields = vec![];
+        let mut columns: Vec<ArrayRef> = vec![];
+        for (name, values) in self.by.iter().zip(by_columns) {
+            fields.push(Field::new(name, DataType::Utf8, true));
+            columns.push(Arc::new(StringArray::from(values)));
+        }
+        fields.push(Field::new("column", DataType::Utf8, false));
+        columns.push(Arc::new(StringArray::from(names)));
+        fields.push(Field::new("describe", DataType::Utf8, false));
+        columns.push(Arc::new(StringArray::from(stats)));
+        fields.push(Field::new("value", DataType::Utf8, true));
+        columns.push(Arc::new(StringArray::from(rendered)));
+
+        let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)?;
+        Ok(batch)
+    }
+
+    /// column name in the describe result, list columns are described by their length
+    /// and labelled as such
+    fn label(&self, name: &str) -> Result<String> {
+        let original = self.original.schema().field_with_unqualified_name(name)?;
+        Ok(match is_list(original.data_type()) {
+            true => format!("length({})", name),
+            false => name.to_string(),
+        })
+    }
+
+    /// statistics of column `name` in the result of `describe`, `None` when it isn't described
+    pub(crate) fn stats_column<'a>(
+        &self,
+        stats: &'a RecordBatch,
+        name: &str,
+    ) -> Option<&'a ArrayRef> {
+        let label = self.label(name).ok()?;
+        let described = self.stat_fields().iter().any(|f| f.name() == name);
+        described.then(|| stats.column_by_name(&label)).flatten()
+    }
+
+    /// statistics in the value domain (min, mean, ...) of temporal columns are shown as
+    /// dates or timestamps, the others (std, count, ...) stay numbers, so such columns
+    /// become text
+    pub fn cast_back(&self, batch: RecordBatch) -> Result<RecordBatch> {
+        let mut fields = vec![batch.schema().field(0).clone()];
+        let mut columns = vec![batch.column(0).clone()];
+        let stat_fields = self.stat_fields();
+        let schema = batch.schema();
+        let labelled = schema.fields().iter().zip(batch.columns()).skip(1);
+        for ((field, array), stat_field) in labelled.zip(stat_fields.iter()) {
+            let original = self
+                .original
+                .schema()
+                .field_with_unqualified_name(stat_field.name())?;
+            if !original.data_type().is_temporal() {
+                fields.push(field.as_ref().clone());
+                columns.push(array.clone());
+                continue;
+            }
+            let values = (0..array.len())
+                .map(|r| float_value(array, r))
+                .collect::<Result<Vec<_>>>()?;
+            let rendered = render_values(&self.methods, values, original.data_type())?;
+            fields.push(Field::new(field.name(), DataType::Utf8, true));
+            columns.push(Arc::new(StringArray::from(rendered)));
+        }
+        let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)?;
+        Ok(batch)
+    }
+
+    /// string (and binary) columns, which are only described by their length in `describe`
+    fn categorical_fields(&self) -> Vec<FieldRef> {
+        self.original
+            .schema()
+            .fields()
+            .iter()
+            .filter(|f| !self.by.contains(f.name()) && is_categorical(f.data_type()))
+            .cloned()
+            .collect()
+    }
+
+    /// what the values of string columns actually are: approx distinct count,
+    /// empty strings, lexical min/max, read from the row of the stats aggregate, and the
+    /// `k` most frequent values, which take a group by per column, so `k` is 0 unless
+    /// asked for
+    async fn categorical(&self, row: &RecordBatch, k: usize) -> Result<Option<RecordBatch>> {
+        let fields = self.categorical_fields();
+        if fields.is_empty() {
+            return Ok(None);
+        }
+        let get = |name: &str, key: &str| row.column_by_name(&stat_name(name, key)).unwrap();
+
+        let mut names = vec![];
+        let (mut counts, mut distincts, mut empties) = (vec![], vec![], vec![]);
+        let (mut mins, mut maxs, mut tops) = (vec![], vec![], vec![]);
+        for field in fields.iter() {
+            let name = field.name();
+            let non_null = float_value(get(name, "count"), 0)?.unwrap_or_default();
+            names.push(name.clone());
+            counts.push(non_null as i64);
+            // the estimate may overshoot, but can't be more than the non-null values
+            let distinct = float_value(get(name, "approx_distinct"), 0)?;
+            distincts.push(distinct.map(|v| v.min(non_null) as i64));
+            empties.push(float_value(get(name, "empty"), 0)?.map(|v| v as i64));
+            mins.push(text_value(get(name, "text_min"))?);
+            maxs.push(text_value(get(name, "text_max"))?);
+            tops.push(self.top_values(name, k, non_null).await?);
+        }
+
+        let mut fields = vec![
+            Field::new("column", DataType::Utf8, false),
+            Field::new("count", DataType::Int64, false),
+            Field::new("approx_distinct", DataType::Int64, true),
+            Field::new("empty_count", DataType::Int64, true),
+            Field::new("min", DataType::Utf8, true),
+            Field::new("max", DataType::Utf8, true),
+        ];
+        let mut columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from(names)),
+            Arc::new(Int64Array::from(counts)),
+            Arc::new(Int64Array::from(distincts)),
+            Arc::new(Int64Array::from(empties)),
+            Arc::new(StringArray::from(mins)),
+            Arc::new(StringArray::from(maxs)),
+        ];
+        if k > 0 {
+            fields.push(Field::new(format!("top_{}", k), DataType::Utf8, true));
+            columns.push(Arc::new(StringArray::from(tops)));
+        }
+        let schema = Schema::new(fields);
+        let batch = RecordBatch::try_new(Arc::new(schema), columns)?;
+        Ok(Some(batch))
+    }
+
+    fn list_fields(&self) -> Vec<FieldRef> {
+        self.original
+            .schema()
+            .fields()
+            .iter()
+            .filter(|f| !self.by.contains(f.name()) && is_list(f.data_type()))
+            .cloned()
+            .collect()
+    }
+
+    /// what list columns contain: the proportion of empty lists, plus statistics over
+    /// the flattened element values, mean and std are only computed for numeric elements
+    pub async fn lists(&self) -> Result<Option<RecordBatch>> {
+        let fields = self.list_fields();
+        if fields.is_empty() {
+            return Ok(None);
+        }
         let expr = fields
-            .map(|field| {
-                let pecentfile = lit(p as f64 / 100.0);
-                approx_percentile_cont::approx_percentile_cont(col(field.name()), pecentfile)
-                    .alias(field.name())
+            .iter()
+            .flat_map(|f| {
+                let name = f.name();
+                let empty = when(array_length(ident(name)).eq(lit(0_u64)), lit(1_i64))
+                    .otherwise(lit(0_i64));
+                [
+                    count(ident(name)).alias(stat_name(name, "count")),
+                    sum(empty.expect("when with otherwise")).alias(stat_name(name, "empty")),
+                ]
             })
             .collect();
-        let df = self.transformed.clone().aggregate(vec![], expr);
-        Ok(df?)
-    }
-
-    impl_describe_method!(count);
-    impl_describe_method!(avg);
-    impl_describe_method!(stddev);
-    impl_describe_method!(min);
-    impl_describe_method!(max);
-    impl_describe_method!(median);
-
-    pub(crate) async fn describe(&self) -> anyhow::Result<DataFrame> {
-        let df: Option<DataFrame> = self.methods.iter().fold(None, |acc, method| {
-            let stat_df = match method {
-                DescribeMethod::Count => self.count(),
-                DescribeMethod::NullCount => self.null_count(),
-                DescribeMethod::Mean => self.avg(),
-                DescribeMethod::Std => self.stddev(),
-                DescribeMethod::Min => self.min(),
-                DescribeMethod::Max => self.max(),
-                DescribeMethod::Median => self.median(),
-                DescribeMethod::Percentile(n) => self.percentile(*n),
-            };
-            let stat_df = stat_df.unwrap();
-            let mut select_expr = vec![lit(method.to_string()).alias("describe")];
-            select_expr.extend(stat_df.schema().fields().iter().map(|f| col(f.name())));
+        let batches = self
+            .original
+            .clone()
+            .aggregate(vec![], expr)?
+            .collect()
+            .await?;
+        let row = batches
+            .iter()
+            .find(|b| b.num_rows() > 0)
+            .ok_or_else(|| anyhow::anyhow!("describe returns no rows"))?;
+        let get = |name: &str, key: &str| row.column_by_name(&stat_name(name, key)).unwrap();
 
-            let stat_df = stat_df.select(select_expr).unwrap();
+        let (mut names, mut counts, mut empties) = (vec![], vec![], vec![]);
+        let (mut elements, mut distincts) = (vec![], vec![]);
+        let (mut mins, mut maxs, mut means, mut stds) = (vec![], vec![], vec![], vec![]);
+        for field in fields.iter() {
+            let name = field.name();
+            let non_null = float_value(get(name, "count"), 0)?.unwrap_or_default();
+            let empty = float_value(get(name, "empty"), 0)?.unwrap_or_default();
+            names.push(name.clone());
+            counts.push(non_null as i64);
+            empties.push((non_null > 0.0).then(|| empty / non_null));
 
-            match acc {
-                Some(acc) => Some(acc.union(stat_df).unwrap()),
-                None => Some(stat_df),
+            let item = match field.data_type() {
+                DataType::List(item) | DataType::LargeList(item) => item.data_type().clone(),
+                dt => dt.clone(),
+            };
+            let e = col("element");
+            let mut expr = vec![count(e.clone()).alias("count")];
+            // elements which are structs themselves are only counted
+            if !is_nested(&item) {
+                expr.push(count_distinct(e.clone()).alias("distinct"));
+                expr.push(min(e.clone()).alias("min"));
+                expr.push(max(e.clone()).alias("max"));
+            }
+            if item.is_numeric() {
+                expr.push(avg(e.clone()).alias("mean"));
+                expr.push(stddev(e).alias("std"));
             }
-        });
+            let batches = self
+                .original
+                .clone()
+                .select(vec![ident(name).alias("element")])?
+                .unnest_columns(&["element"])?
+                .aggregate(vec![], expr)?
+                .collect()
+                .await?;
+            let row = batches
+                .iter()
+                .find(|b| b.num_rows() > 0)
+                .ok_or_else(|| anyhow::anyhow!("describe returns no rows"))?;
+            // statistics which are not computed for the element type are left empty
+            let get = |key: &str| -> Result<Option<f64>> {
+                match row.column_by_name(key) {
+                    Some(array) => float_value(array, 0),
+                    None => Ok(None),
+                }
+            };
+            let text = |key: &str| -> Result<Option<String>> {
+                match row.column_by_name(key) {
+                    Some(array) => text_value(array),
+                    None => Ok(None),
+                }
+            };
+            elements.push(get("count")?.map(|v| v as i64));
+            distincts.push(get("distinct")?.map(|v| v as i64));
+            mins.push(text("min")?);
+            maxs.push(text("max")?);
+            means.push(get("mean")?);
+            stds.push(get("std")?);
+        }
 
-        df.ok_or_else(|| anyhow::anyhow!("No describe found"))
+        let schema = Schema::new(vec![
+            Field::new("column", DataType::Utf8, false),
+            Field::new("count", DataType::Int64, false),
+            Field::new("empty_ratio", DataType::Float64, true),
+            Field::new("elements", DataType::Int64, true),
+            Field::new("distinct_elements", DataType::Int64, true),
+            Field::new("element_min", DataType::Utf8, true),
+            Field::new("element_max", DataType::Utf8, true),
+            Field::new("element_mean", DataType::Float64, true),
+            Field::new("element_std", DataType::Float64, true),
+        ]);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from(names)),
+            Arc::new(Int64Array::from(counts)),
+            Arc::new(Float64Array::from(empties)),
+            Arc::new(Int64Array::from(elements)),
+            Arc::new(Int64Array::from(distincts)),
+            Arc::new(StringArray::from(mins)),
+            Arc::new(StringArray::from(maxs)),
+            Arc::new(Float64Array::from(means)),
+            Arc::new(Float64Array::from(stds)),
+        ];
+        let batch = RecordBatch::try_new(Arc::new(schema), columns)?;
+        Ok(Some(batch))
     }
 
-    /// if the original col type is date, then we will convert it to date
-    pub fn cast_back(&self, df: DataFrame) -> DataFrame {
-        let desc = Arc::new(Field::new("describe", DataType::Utf8, true));
-        let mut fields = vec![&desc];
-        fields.extend(self.original.schema().fields().iter());
-        let expr = fields
+    /// the `k` most frequent values of a column with count and percentage, one per line
+    async fn top_values(&self, name: &str, k: usize, non_null: f64) -> Result<Option<String>> {
+        if k == 0 {
+            return Ok(None);
+        }
+        let values = frequent_values(&self.original, cast(ident(name), DataType::Utf8), k).await?;
+        let lines: Vec<String> = values
             .iter()
-            .map(|field| {
-                let dt = field.data_type();
-                let expr = match dt {
-                    dt if dt.is_temporal() => cast(col(field.name()), dt.clone()),
-                    DataType::List(_) | DataType::LargeList(_) => {
-                        cast(col(field.name()), DataType::Int32)
-                    }
-                    _ => col(field.name()),
-                };
-                expr.alias(field.name())
-            })
+            .map(|(value, freq)| format!("{}: {} ({:.1}%)", value, freq, freq / non_null * 100.0))
             .collect();
+        Ok(Some(lines.join("\n")))
+    }
+}
 
-        df.select(expr)
-            .unwrap()
-            .sort(vec![col("describe").sort(true, true)])
+/// the `k` most frequent non-null values of `expr` with their count
+pub(crate) async fn frequent_values(
+    df: &DataFrame,
+    expr: Expr,
+    k: usize,
+) -> Result<Vec<(String, f64)>> {
+    let batches = df
+        .clone()
+        .select(vec![expr.alias("value")])?
+        .filter(col("value").is_not_null())?
+        .aggregate(vec![col("value")], vec![count(lit(1)).alias("freq")])?
+        .sort(vec![
+            col("freq").sort(false, false),
+            col("value").sort(true, false),
+        ])?
+        .limit(0, Some(k))?
+        .collect()
+        .await?;
+    let mut values = vec![];
+    for batch in batches.iter() {
+        for r in 0..batch.num_rows() {
+            let value = array_value_to_string(batch.column(0), r)?;
+            let freq = float_value(batch.column(1), r)?.unwrap_or_default();
+            values.push((value, freq));
+        }
+    }
+    Ok(values)
+}
+
+/// render statistics of a column as text, only statistics in the value domain
+/// (min, mean, ...) of a temporal column are shown as dates
+pub(crate) fn render_values(
+    methods: &[DescribeMethod],
+    values: Vec<Option<f64>>,
+    dt: &DataType,
+) -> Result<Vec<Option<String>>> {
+    let values: ArrayRef = Arc::new(Float64Array::from(values));
+    let cast_back = cast_array(&cast_back_array(&values, dt)?, &DataType::Utf8)?;
+    let cast_back = cast_back.as_any().downcast_ref::<StringArray>().unwrap();
+    let plain = cast_array(&values, &DataType::Utf8)?;
+    let plain = plain.as_any().downcast_ref::<StringArray>().unwrap();
+    let rendered = methods
+        .iter()
+        .enumerate()
+        .map(|(j, method)| {
+            let array = match method.in_value_domain() {
+                true => cast_back,
+                false => plain,
+            };
+            array.is_valid(j).then(|| array.value(j).to_string())
+        })
+        .collect();
+    Ok(rendered)
+}
+
+/// result of `describe`: numeric statistics, plus a categorical section for string columns,
+/// an element section for list columns and optional sparklines for numeric columns
+#[derive(Debug)]
+pub struct DescribeReport {
+    pub stats: RecordBatch,
+    pub categorical: Option<RecordBatch>,
+    pub lists: Option<RecordBatch>,
+    pub sparklines: Option<RecordBatch>,
+}
+
+pub(crate) fn is_categorical(dt: &DataType) -> bool {
+    matches!(
+        dt,
+        DataType::Utf8
+            | DataType::LargeUtf8
+            | DataType::Utf8View
+            | DataType::Binary
+            | DataType::LargeBinary
+    )
+}
+
+pub(crate) fn is_list(dt: &DataType) -> bool {
+    matches!(dt, DataType::List(_) | DataType::LargeList(_))
+}
+
+fn text_value(array: &ArrayRef) -> Result<Option<String>> {
+    Ok(array
+        .is_valid(0)
+        .then(|| array_value_to_string(array, 0))
+        .transpose()?)
+}
+
+/// aggregates of the categorical section over the text values of a string column
+fn categorical_exprs(values: &str) -> Vec<(&'static str, Expr)> {
+    let text = ident(values);
+    let empty = when(text.clone().eq(lit("")), lit(1_i64))
+        .otherwise(lit(0_i64))
+        .expect("when with otherwise");
+    vec![
+        ("count", count(text.clone())),
+        ("approx_distinct", approx_distinct(text.clone())),
+        ("empty", sum(empty)),
+        ("text_min", min(text.clone())),
+        ("text_max", max(text)),
+    ]
+}
+
+fn first_row(batches: &[RecordBatch]) -> Result<&RecordBatch> {
+    batches
+        .iter()
+        .find(|b| b.num_rows() > 0)
+        .ok_or_else(|| anyhow::anyhow!("describe returns no rows"))
+}
+
+/// the most frequent value of a column, string columns keep their values instead of
+/// the length they are described by
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+enum ModeValue {
+    Number(f64),
+    Text(String),
+}
+
+fn text_mode(modes: Option<&Vec<Option<ModeValue>>>, i: usize) -> Option<String> {
+    match modes.and_then(|m| m[i].as_ref()) {
+        Some(ModeValue::Text(s)) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+/// the transformed column, plus the values as text of string columns for `distinct_count`
+/// and `mode`, which are about the values rather than the lengths
+fn described(field: &Field) -> Vec<Expr> {
+    let mut expr = vec![transform(field)];
+    if is_categorical(field.data_type()) {
+        expr.push(cast(ident(field.name()), DataType::Utf8).alias(values_name(field.name())));
+    }
+    expr
+}
+
+fn values_name(name: &str) -> String {
+    format!("{}#values", name)
+}
+
+/// numeric columns are described as is, temporal as float, boolean as 0/1 so the mean is
+/// the ratio of true, list and string by their length
+pub(crate) fn transform(field: &Field) -> Expr {
+    let dt = field.data_type();
+    let expr = match dt {
+        dt if dt.is_temporal() => cast(ident(field.name()), DataType::Float64),
+        dt if dt.is_numeric() => ident(field.name()),
+        DataType::Boolean => cast(ident(field.name()), DataType::Int8),
+        DataType::List(_) | DataType::LargeList(_) => array_length(ident(field.name())),
+        _ => length(ident(field.name())),
+    };
+    expr.alias(field.name())
+}
+
+fn cast_back_array(array: &ArrayRef, dt: &DataType) -> Result<ArrayRef> {
+    let array = match dt {
+        dt if dt.is_temporal() => cast_array(&cast_array(array, &DataType::Int64)?, dt)?,
+        _ => array.clone(),
+    };
+    Ok(array)
+}
+
+/// rendered values of the group columns, `None` for null so a null group doesn't
+/// match an empty string
+type GroupKey = Vec<Option<String>>;
+
+/// values of the first `n` columns of row `r`, used to match groups between aggregates
+fn group_key(batch: &RecordBatch, r: usize, n: usize) -> Result<GroupKey> {
+    let values = (0..n)
+        .map(|i| {
+            let array = batch.column(i);
+            array
+                .is_valid(r)
+                .then(|| array_value_to_string(array, r))
+                .transpose()
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(values)
+}
+
+/// alias of the shared row count in the stats aggregate
+const ROWS: &str = "__rows";
+
+fn stat_name(name: &str, key: &str) -> String {
+    format!("{}__{}", name, key)
+}
+
+/// key of a statistic which is read back directly from the stats aggregate
+fn stat_key(method: &DescribeMethod) -> String {
+    match method {
+        DescribeMethod::Percentile(p) => format!("p{}", p),
+        method => method.to_string(),
+    }
+}
+
+/// aggregate expressions needed by a statistic, keyed for `stat_name`, `values` is the
+/// column of the original values that `distinct_count` counts
+fn stat_exprs(method: &DescribeMethod, name: &str, values: &str) -> Vec<(String, Expr)> {
+    let x = ident(name);
+    let key = stat_key(method);
+    match method {
+        DescribeMethod::Count | DescribeMethod::NullCount => vec![("count".into(), count(x))],
+        DescribeMethod::DistinctCount => vec![(key, count_distinct(ident(values)))],
+        DescribeMethod::Mean => vec![(key, avg(x))],
+        DescribeMethod::Std => vec![(key, stddev(x))],
+        DescribeMethod::Variance => vec![(key, var_sample(x))],
+        DescribeMethod::Min => vec![(key, min(x))],
+        DescribeMethod::Max => vec![(key, max(x))],
+        DescribeMethod::Sum => vec![(key, sum(x))],
+        DescribeMethod::Median => vec![(key, median(x))],
+        DescribeMethod::Percentile(p) => {
+            let percentile = lit(*p as f64 / 100.0);
+            vec![(
+                key,
+                approx_percentile_cont::approx_percentile_cont(x, percentile),
+            )]
+        }
+        DescribeMethod::Skew => vec![(key, skew(cast(x, DataType::Float64)))],
+        DescribeMethod::Kurtosis => vec![(key, kurtosis(cast(x, DataType::Float64)))],
+        DescribeMethod::Mode => vec![],
+    }
+}
+
+/// value at row `r` of an array as f64
+pub(crate) fn float_value(array: &ArrayRef, r: usize) -> Result<Option<f64>> {
+    let array = cast_array(array, &DataType::Float64)?;
+    let array = array
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .expect("cast to Float64");
+    Ok((r < array.len() && array.is_valid(r)).then(|| array.value(r)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{BooleanArray, ListArray, TimestampMicrosecondArray};
+    use arrow::datatypes::Int64Type;
+    use datafusion::prelude::SessionContext;
+
+    fn dataframe(columns: Vec<(&str, ArrayRef)>) -> Result<DataFrame> {
+        let batch = RecordBatch::try_from_iter(columns)?;
+        Ok(SessionContext::new().read_batch(batch)?)
+    }
+
+    /// value of statistic `method` of column `label` in the result of `describe`
+    fn stat(stats: &RecordBatch, label: &str, method: &str) -> Option<String> {
+        let methods = stats.column(0).as_any().downcast_ref::<StringArray>()?;
+        let r = methods.iter().position(|m| m == Some(method))?;
+        let column = stats.column_by_name(label)?;
+        column
+            .is_valid(r)
+            .then(|| array_value_to_string(column, r).unwrap())
+    }
+
+    #[tokio::test]
+    async fn skew_and_kurtosis_should_keep_precision_on_large_offsets() -> Result<()> {
+        // epoch seconds a minute apart, uniformly distributed
+        let seconds = (0..10_000).map(|i| 1.7e9 + i as f64 * 60.0);
+        let df = dataframe(vec![(
+            "ts",
+            Arc::new(Float64Array::from_iter_values(seconds)),
+        )])?;
+        let describer = DataFrameDescriber::try_new(df)?
+            .with_methods(vec![DescribeMethod::Skew, DescribeMethod::Kurtosis]);
+        let stats = describer.describe().await?;
+        let value = |method: &str| -> f64 { stat(&stats, "ts", method).unwrap().parse().unwrap() };
+        assert!(value("skew").abs() < 1e-6, "skew {}", value("skew"));
+        assert!(
+            (value("kurtosis") + 1.2).abs() < 1e-3,
+            "kurtosis {}",
+            value("kurtosis")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cast_back_should_only_render_value_statistics_as_timestamps() -> Result<()> {
+        let day = 86_400_000_000_i64;
+        let micros = (0..10).map(|i| 1_700_000_000_000_000 + i * day);
+        let df = dataframe(vec![(
+            "at",
+            Arc::new(TimestampMicrosecondArray::from_iter_values(micros)),
+        )])?;
+        let methods = ["count", "mean", "std", "min", "max", "sum", "p50"];
+        let describer = DataFrameDescriber::try_new(df)?.with_methods(
+            methods
+                .iter()
+                .map(|m| m.parse().unwrap())
+                .collect::<Vec<_>>(),
+        );
+        let stats = describer.cast_back(describer.describe().await?)?;
+        assert_eq!(
+            stats.column_by_name("at").unwrap().data_type(),
+            &DataType::Utf8
+        );
+        assert_eq!(stat(&stats, "at", "min").unwrap(), "2023-11-14T22:13:20");
+        assert_eq!(stat(&stats, "at", "max").unwrap(), "2023-11-23T22:13:20");
+        assert!(stat(&stats, "at", "mean")
             .unwrap()
+            .starts_with("2023-11-19"));
+        // statistics about the spread or the amount stay numbers
+        assert_eq!(stat(&stats, "at", "count").unwrap(), "10.0");
+        let std: f64 = stat(&stats, "at", "std").unwrap().parse().unwrap();
+        assert!((std / day as f64 - 3.0277).abs() < 1e-3, "std {}", std);
+        assert!(stat(&stats, "at", "sum").unwrap().parse::<f64>().is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn describe_should_count_true_values_of_bool_columns() -> Result<()> {
+        let flags = BooleanArray::from(vec![Some(true), Some(false), Some(true), None]);
+        let df = dataframe(vec![("active", Arc::new(flags) as ArrayRef)])?;
+        let methods = ["count", "null_count", "mean", "min", "max", "sum"];
+        let describer = DataFrameDescriber::try_new(df)?.with_methods(
+            methods
+                .iter()
+                .map(|m| m.parse().unwrap())
+                .collect::<Vec<_>>(),
+        );
+        let stats = describer.cast_back(describer.describe().await?)?;
+        let value = |method: &str| stat(&stats, "active", method).unwrap();
+        assert_eq!(value("count"), "3.0");
+        assert_eq!(value("null_count"), "1.0");
+        assert!(value("mean").starts_with("0.666"));
+        assert_eq!(value("min"), "0.0");
+        assert_eq!(value("max"), "1.0");
+        assert_eq!(value("sum"), "2.0");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lists_should_count_elements_empty_and_null_lists() -> Result<()> {
+        let tags = ListArray::from_iter_primitive::<Int64Type, _, _>([
+            Some(vec![Some(1), Some(2)]),
+            Some(vec![]),
+            None,
+            Some(vec![Some(3), None, Some(4), Some(4)]),
+        ]);
+        let df = dataframe(vec![
+            (
+                "id",
+                Arc::new(Int64Array::from(vec![1, 2, 3, 4])) as ArrayRef,
+            ),
+            ("tags", Arc::new(tags)),
+        ])?;
+        let lists = DataFrameDescriber::try_new(df)?.lists().await?.unwrap();
+        // only list columns are described
+        assert_eq!(lists.num_rows(), 1);
+        let value = |name: &str| array_value_to_string(lists.column_by_name(name).unwrap(), 0);
+        assert_eq!(value("column")?, "tags");
+        // null lists are not counted, empty ones are
+        assert_eq!(value("count")?, "3");
+        assert_eq!(value("empty_ratio")?, (1.0_f64 / 3.0).to_string());
+        // null elements are not counted either
+        assert_eq!(value("elements")?, "5");
+        assert_eq!(value("distinct_elements")?, "4");
+        assert_eq!(value("element_min")?, "1");
+        assert_eq!(value("element_max")?, "4");
+        assert_eq!(value("element_mean")?, "2.8");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lists_should_be_none_without_list_columns() -> Result<()> {
+        let df = dataframe(vec![(
+            "id",
+            Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef,
+        )])?;
+        assert!(DataFrameDescriber::try_new(df)?.lists().await?.is_none());
+        Ok(())
     }
 }
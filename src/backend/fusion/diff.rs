@@ -0,0 +1,475 @@
+//! diff: 按key比较两个数据集, 比如昨天和今天导出的同一张表
+//!
+//! 两边的列改名为 `a.列名` / `b.列名` 后做full outer join(null的key互相匹配), 一次aggregate
+//! 算出新增/删除/修改的行数和每一列修改的行数; 修改的行按列展开成 key, column, old, new 取样展示
+
+use std::{collections::HashMap, ops::Not, sync::Arc};
+
+use anyhow::{bail, Result};
+use arrow::{
+    array::{Array, Int64Array, RecordBatch, StringArray},
+    datatypes::{DataType, Field, Schema},
+    util::pretty::pretty_format_batches,
+};
+use datafusion::{
+    common::Column,
+    functions_aggregate::{count::count, sum::sum},
+    logical_expr::{
+        binary_expr, type_coercion::binary::comparison_coercion, JoinType, LogicalPlanBuilder,
+        Operator,
+    },
+    prelude::{cast, ident, lit, when, DataFrame, Expr},
+};
+
+/// marks the rows of each side, so a null key still counts as present
+const A_ROW: &str = "a.#row";
+const B_ROW: &str = "b.#row";
+
+#[derive(Debug)]
+pub struct Diff {
+    /// columns added, removed or with another type, `None` when the schemas match
+    pub schema: Option<RecordBatch>,
+    pub rows: RecordBatch,
+    /// rows changed per column, columns compared only
+    pub columns: Option<RecordBatch>,
+    pub sample: Vec<RecordBatch>,
+    /// e.g. duplicate keys, which make the counts unreliable
+    pub warnings: Vec<String>,
+}
+
+/// compare `b` against `a` by `keys`, `limit` changed values are shown
+pub async fn diff(
+    (a_name, a): (&str, DataFrame),
+    (b_name, b): (&str, DataFrame),
+    keys: &[String],
+    limit: usize,
+) -> Result<Diff> {
+    let a_types = types(&a);
+    let b_types = types(&b);
+    for key in keys {
+        for (name, types) in [(a_name, &a_types), (b_name, &b_types)] {
+            if !types.iter().any(|(n, _)| n == key) {
+                bail!("key column {} not found in {}", key, name);
+            }
+        }
+    }
+    let b_type = |name: &str| b_types.iter().find(|(n, _)| n == name).map(|(_, t)| t);
+    // columns on both sides with the type they are compared as, text when a changed
+    // type can't be compared, e.g. a struct turned into a string
+    let compared: Vec<(String, DataType)> = a_types
+        .iter()
+        .filter(|(name, _)| !keys.contains(name))
+        .filter_map(|(name, t)| {
+            let bt = b_type(name)?;
+            let common = comparison_coercion(t, bt).unwrap_or(DataType::Utf8);
+            Some((name.clone(), common))
+        })
+        .collect();
+
+    let mut warnings = vec![];
+    for (name, df) in [(a_name, &a), (b_name, &b)] {
+        let duplicates = duplicate_keys(df.clone(), keys).await?;
+        if duplicates > 0 {
+            warnings.push(format!(
+                "{} keys of {} appear more than once, the counts below include every combination",
+                duplicates, name
+            ));
+        }
+    }
+
+    let joined = join(a, b, keys)?;
+    let matched = ident(A_ROW).is_not_null().and(ident(B_ROW).is_not_null());
+    let changed_cols = compared
+        .iter()
+        .map(|(name, t)| (name.as_str(), changed(name, t)))
+        .collect::<Vec<_>>();
+    let any_changed = changed_cols
+        .iter()
+        .map(|(_, e)| e.clone())
+        .reduce(|a, b| a.or(b))
+        .unwrap_or(lit(false));
+    let flag = |e: Expr| -> Result<Expr> { Ok(sum(when(e, lit(1_i64)).otherwise(lit(0_i64))?)) };
+    let mut aggs = vec![
+        flag(ident(A_ROW).is_null())?.alias("added"),
+        flag(ident(B_ROW).is_null())?.alias("removed"),
+        flag(matched.clone().and(any_changed.clone()))?.alias("changed"),
+        flag(matched.clone().and(any_changed.not()))?.alias("unchanged"),
+    ];
+    for (name, e) in changed_cols.iter() {
+        aggs.push(flag(matched.clone().and(e.clone()))?.alias(changed_alias(name)));
+    }
+    let counts = joined.clone().aggregate(vec![], aggs)?.collect().await?;
+    let counts = counts.first().cloned();
+    let value = |name: &str| -> i64 {
+        counts
+            .as_ref()
+            .and_then(|batch| batch.column_by_name(name))
+            .and_then(|c| c.as_any().downcast_ref::<Int64Array>().cloned())
+            .filter(|c| !c.is_empty() && c.is_valid(0))
+            .map(|c| c.value(0))
+            .unwrap_or_default()
+    };
+
+    let statuses = ["added", "removed", "changed", "unchanged"];
+    let rows = counts_batch(
+        ("status", "rows"),
+        statuses.iter().map(|s| (s.to_string(), value(s))).collect(),
+    )?;
+    let columns = match compared.is_empty() {
+        true => None,
+        false => Some(counts_batch(
+            ("column", "changed_rows"),
+            compared
+                .iter()
+                .map(|(name, _)| (name.clone(), value(&changed_alias(name))))
+                .collect(),
+        )?),
+    };
+
+    let mut sample: Option<DataFrame> = None;
+    for (name, e) in changed_cols.iter() {
+        if value(&changed_alias(name)) == 0 {
+            continue;
+        }
+        let mut exprs = keys
+            .iter()
+            .map(|k| ident(format!("a.{}", k)).alias(k))
+            .collect::<Vec<_>>();
+        exprs.extend([
+            lit(*name).alias("column"),
+            cast(ident(format!("a.{}", name)), DataType::Utf8).alias("old"),
+            cast(ident(format!("b.{}", name)), DataType::Utf8).alias("new"),
+        ]);
+        let df = joined
+            .clone()
+            .filter(matched.clone().and(e.clone()))?
+            .select(exprs)?;
+        sample = Some(match sample {
+            None => df,
+            Some(sample) => sample.union(df)?,
+        });
+    }
+    let sample = match sample {
+        Some(df) => {
+            let mut order = keys
+                .iter()
+                .map(|k| ident(k).sort(true, false))
+                .collect::<Vec<_>>();
+            order.push(ident("column").sort(true, false));
+            df.sort(order)?.limit(0, Some(limit))?.collect().await?
+        }
+        None => vec![],
+    };
+
+    Ok(Diff {
+        schema: schema_changes((a_name, &a_types), (b_name, &b_types))?,
+        rows,
+        columns,
+        sample,
+        warnings,
+    })
+}
+
+impl Diff {
+    pub fn render(&self) -> Result<String> {
+        let mut sections = vec![];
+        for warning in self.warnings.iter() {
+            sections.push(format!("warning: {}", warning));
+        }
+        match &self.schema {
+            Some(schema) => sections.push(format!(
+                "schema:\n{}",
+                pretty_format_batches(std::slice::from_ref(schema))?
+            )),
+            None => sections.push("schema: identical".to_string()),
+        }
+        sections.push(format!(
+            "rows:\n{}",
+            pretty_format_batches(std::slice::from_ref(&self.rows))?
+        ));
+        if let Some(columns) = &self.columns {
+            sections.push(format!(
+                "changed rows per column:\n{}",
+                pretty_format_batches(std::slice::from_ref(columns))?
+            ));
+        }
+        if !self.sample.is_empty() {
+            sections.push(format!(
+                "changed values (sample):\n{}",
+                pretty_format_batches(&self.sample)?
+            ));
+        }
+        Ok(sections.join("\n\n"))
+    }
+}
+
+fn types(df: &DataFrame) -> Vec<(String, DataType)> {
+    df.schema()
+        .fields()
+        .iter()
+        .map(|f| (f.name().clone(), f.data_type().clone()))
+        .collect()
+}
+
+/// both sides with their columns renamed to `a.name` and `b.name`, joined on the keys; a null
+/// key matches a null key (`IS NOT DISTINCT FROM`), so rows without a key aren't added and removed
+fn join(a: DataFrame, b: DataFrame, keys: &[String]) -> Result<DataFrame> {
+    let rename = |df: DataFrame, side: &str, row: &str| -> Result<DataFrame> {
+        let mut exprs = df
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| ident(f.name()).alias(format!("{}.{}", side, f.name())))
+            .collect::<Vec<_>>();
+        exprs.push(lit(true).alias(row));
+        Ok(df.select(exprs)?)
+    };
+    let on = |side: &str| {
+        keys.iter()
+            .map(|k| Column::from_name(format!("{}.{}", side, k)))
+            .collect::<Vec<_>>()
+    };
+    // `join_on` with IS NOT DISTINCT FROM would be a nested loop join, a hash join can match nulls
+    let (state, a) = rename(a, "a", A_ROW)?.into_parts();
+    let b = rename(b, "b", B_ROW)?.into_unoptimized_plan();
+    let plan = LogicalPlanBuilder::from(a)
+        .join_detailed(b, JoinType::Full, (on("a"), on("b")), None, true)?
+        .build()?;
+    Ok(DataFrame::new(state, plan))
+}
+
+/// whether the column differs between the sides of a matched row, nulls are equal
+fn changed(name: &str, data_type: &DataType) -> Expr {
+    let a = cast(ident(format!("a.{}", name)), data_type.clone());
+    let b = cast(ident(format!("b.{}", name)), data_type.clone());
+    binary_expr(a, Operator::IsDistinctFrom, b)
+}
+
+/// name of the changed rows count of a column, apart from the status counts
+fn changed_alias(name: &str) -> String {
+    format!("#{}", name)
+}
+
+/// number of keys that appear more than once
+async fn duplicate_keys(df: DataFrame, keys: &[String]) -> Result<usize> {
+    let group = keys.iter().map(ident).collect::<Vec<_>>();
+    let n = df
+        .aggregate(group, vec![count(lit(1)).alias("n")])?
+        .filter(ident("n").gt(lit(1_i64)))?
+        .count()
+        .await?;
+    Ok(n)
+}
+
+fn schema_changes(
+    (a_name, a): (&str, &[(String, DataType)]),
+    (b_name, b): (&str, &[(String, DataType)]),
+) -> Result<Option<RecordBatch>> {
+    let a_map: HashMap<&str, &DataType> = a.iter().map(|(n, t)| (n.as_str(), t)).collect();
+    let b_map: HashMap<&str, &DataType> = b.iter().map(|(n, t)| (n.as_str(), t)).collect();
+    let mut changes: Vec<(&str, Option<String>, Option<String>, &str)> = vec![];
+    for (name, t) in a {
+        match b_map.get(name.as_str()) {
+            None => changes.push((name, Some(t.to_string()), None, "removed")),
+            Some(bt) if *bt != t => changes.push((
+                name,
+                Some(t.to_string()),
+                Some(bt.to_string()),
+                "type changed",
+            )),
+            Some(_) => {}
+        }
+    }
+    for (name, t) in b {
+        if !a_map.contains_key(name.as_str()) {
+            changes.push((name, None, Some(t.to_string()), "added"));
+        }
+    }
+    if changes.is_empty() {
+        return Ok(None);
+    }
+    let schema = Schema::new(vec![
+        Field::new("column", DataType::Utf8, false),
+        Field::new(a_name, DataType::Utf8, true),
+        Field::new(b_name, DataType::Utf8, true),
+        Field::new("change", DataType::Utf8, false),
+    ]);
+    let batch = RecordBatch::try_new(
+        Arc::new(schema),
+        vec![
+            Arc::new(StringArray::from_iter_values(changes.iter().map(|c| c.0))),
+            Arc::new(StringArray::from_iter(changes.iter().map(|c| c.1.clone()))),
+            Arc::new(StringArray::from_iter(changes.iter().map(|c| c.2.clone()))),
+            Arc::new(StringArray::from_iter_values(changes.iter().map(|c| c.3))),
+        ],
+    )?;
+    Ok(Some(batch))
+}
+
+fn counts_batch(header: (&str, &str), counts: Vec<(String, i64)>) -> Result<RecordBatch> {
+    let schema = Schema::new(vec![
+        Field::new(header.0, DataType::Utf8, false),
+        Field::new(header.1, DataType::Int64, false),
+    ]);
+    let batch = RecordBatch::try_new(
+        Arc::new(schema),
+        vec![
+            Arc::new(StringArray::from_iter_values(
+                counts.iter().map(|(name, _)| name.as_str()),
+            )),
+            Arc::new(Int64Array::from_iter_values(counts.iter().map(|(_, n)| *n))),
+        ],
+    )?;
+    Ok(batch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::util::display::array_value_to_string;
+    use datafusion::prelude::SessionContext;
+
+    async fn table(ctx: &SessionContext, rows: &str) -> Result<DataFrame> {
+        let sql = format!("select * from (values {}) t(id, name, age)", rows);
+        Ok(ctx.sql(&sql).await?)
+    }
+
+    async fn diff_of(a: &str, b: &str) -> Result<Diff> {
+        let ctx = SessionContext::new();
+        let (a, b) = (table(&ctx, a).await?, table(&ctx, b).await?);
+        diff(("a", a), ("b", b), &["id".to_string()], 10).await
+    }
+
+    /// every row of the batches, values as text
+    fn rows(batches: &[RecordBatch]) -> Vec<Vec<String>> {
+        batches
+            .iter()
+            .flat_map(|batch| {
+                (0..batch.num_rows()).map(|row| {
+                    batch
+                        .columns()
+                        .iter()
+                        .map(|c| array_value_to_string(c, row).unwrap_or_default())
+                        .collect()
+                })
+            })
+            .collect()
+    }
+
+    fn counts(batch: &RecordBatch) -> Vec<String> {
+        rows(std::slice::from_ref(batch))
+            .into_iter()
+            .map(|row| row.join(" "))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn diff_should_count_and_sample_changes() -> Result<()> {
+        let diff = diff_of(
+            "(1, 'ann', 30), (2, 'bob', 40), (3, 'cat', 50)",
+            "(1, 'ann', 31), (2, 'bobby', 40), (4, 'dan', 20)",
+        )
+        .await?;
+        assert!(diff.schema.is_none());
+        assert!(diff.warnings.is_empty());
+        assert_eq!(
+            counts(&diff.rows),
+            ["added 1", "removed 1", "changed 2", "unchanged 0"]
+        );
+        assert_eq!(counts(&diff.columns.unwrap()), ["name 1", "age 1"]);
+        assert_eq!(
+            rows(&diff.sample),
+            [["1", "age", "30", "31"], ["2", "name", "bob", "bobby"]]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn diff_of_a_dataset_with_itself_should_be_unchanged() -> Result<()> {
+        let rows = "(1, 'ann', 30), (2, null, 40), (null, 'cat', null)";
+        let diff = diff_of(rows, rows).await?;
+        assert!(diff.schema.is_none());
+        assert_eq!(
+            counts(&diff.rows),
+            ["added 0", "removed 0", "changed 0", "unchanged 3"]
+        );
+        assert_eq!(counts(&diff.columns.unwrap()), ["name 0", "age 0"]);
+        assert!(diff.sample.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn null_keys_should_match_each_other() -> Result<()> {
+        let diff = diff_of(
+            "(null, 'ann', 30), (1, 'bob', 40)",
+            "(null, 'ann', 31), (1, 'bob', 40)",
+        )
+        .await?;
+        assert_eq!(
+            counts(&diff.rows),
+            ["added 0", "removed 0", "changed 1", "unchanged 1"]
+        );
+        assert_eq!(rows(&diff.sample), [["", "age", "30", "31"]]);
+
+        // two null keys on a side are duplicates like any other key
+        let diff = diff_of("(null, 'ann', 30), (null, 'bob', 40)", "(null, 'ann', 30)").await?;
+        assert_eq!(
+            diff.warnings,
+            ["1 keys of a appear more than once, the counts below include every combination"]
+        );
+        Ok(())
+    }
+
+    fn columns(columns: &[(&str, DataType)]) -> Vec<(String, DataType)> {
+        columns
+            .iter()
+            .map(|(name, t)| (name.to_string(), t.clone()))
+            .collect()
+    }
+
+    fn values(batch: &RecordBatch, i: usize) -> Vec<Option<&str>> {
+        let column = batch.column(i).as_any().downcast_ref::<StringArray>();
+        column.unwrap().iter().collect()
+    }
+
+    #[test]
+    fn schema_changes_should_be_none_for_same_columns() -> Result<()> {
+        let a = columns(&[("id", DataType::Int64), ("email", DataType::Utf8)]);
+        let b = columns(&[("email", DataType::Utf8), ("id", DataType::Int64)]);
+        assert!(schema_changes(("before", &a), ("after", &b))?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn schema_changes_should_list_removed_changed_and_added_columns() -> Result<()> {
+        let a = columns(&[
+            ("id", DataType::Int64),
+            ("name", DataType::Utf8),
+            ("age", DataType::Int32),
+        ]);
+        let b = columns(&[
+            ("id", DataType::Int64),
+            ("age", DataType::Int64),
+            ("kit number", DataType::Int64),
+        ]);
+        let batch = schema_changes(("before", &a), ("after", &b))?.unwrap();
+        let names = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["column", "before", "after", "change"]);
+        assert_eq!(
+            values(&batch, 0),
+            [Some("name"), Some("age"), Some("kit number")]
+        );
+        assert_eq!(values(&batch, 1), [Some("Utf8"), Some("Int32"), None]);
+        assert_eq!(values(&batch, 2), [None, Some("Int64"), Some("Int64")]);
+        assert_eq!(
+            values(&batch, 3),
+            [Some("removed"), Some("type changed"), Some("added")]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+//! explain: 逻辑计划, 优化后的逻辑计划和物理计划, 都以缩进树展示
+//!
+//! `--analyze` 会真正执行查询, 在每个算子后面附上行数, 计算耗时, spill的字节数,
+//! 以及parquet扫描时被统计信息/bloom filter裁剪掉的row group, 用来确认谓词下推是否生效
+
+use std::{fmt::Write, sync::Arc};
+
+use anyhow::Result;
+use datafusion::{
+    physical_plan::{collect, displayable, ExecutionPlan},
+    prelude::{DataFrame, SessionContext},
+};
+
+/// metrics shown by `--analyze`, `--verbose` shows all of them
+const METRICS: &[&str] = &[
+    "output_rows",
+    "elapsed_compute",
+    "spilled_bytes",
+    "bytes_scanned",
+    "row_groups_pruned_statistics",
+    "row_groups_pruned_bloom_filter",
+    "pushdown_rows_filtered",
+    "page_index_rows_filtered",
+];
+
+#[derive(Debug)]
+pub struct Explain {
+    pub logical: String,
+    pub optimized: String,
+    pub physical: String,
+    pub analyzed: bool,
+}
+
+/// plans of the query, executed first when `analyze` so the physical plan carries metrics
+pub async fn explain(
+    ctx: &SessionContext,
+    df: DataFrame,
+    analyze: bool,
+    verbose: bool,
+) -> Result<Explain> {
+    let logical = df.logical_plan().clone();
+    let optimized = ctx.state().optimize(&logical)?;
+    let physical = ctx.state().create_physical_plan(&logical).await?;
+    if analyze {
+        collect(physical.clone(), ctx.task_ctx()).await?;
+    }
+    let (logical, optimized) = match verbose {
+        true => (
+            logical.display_indent_schema().to_string(),
+            optimized.display_indent_schema().to_string(),
+        ),
+        false => (
+            logical.display_indent().to_string(),
+            optimized.display_indent().to_string(),
+        ),
+    };
+    let mut tree = String::new();
+    physical_tree(&physical, 0, analyze, verbose, &mut tree)?;
+    Ok(Explain {
+        logical,
+        optimized,
+        physical: tree,
+        analyzed: analyze,
+    })
+}
+
+impl Explain {
+    pub fn render(&self) -> String {
+        let physical = match self.analyzed {
+            true => "physical plan (analyzed)",
+            false => "physical plan",
+        };
+        [
+            ("logical plan", &self.logical),
+            ("optimized logical plan", &self.optimized),
+            (physical, &self.physical),
+        ]
+        .iter()
+        .map(|(title, plan)| format!("{}:\n{}", title, plan.trim_end()))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+    }
+}
+
+/// one operator per line, children indented under their parent
+fn physical_tree(
+    plan: &Arc<dyn ExecutionPlan>,
+    depth: usize,
+    analyze: bool,
+    verbose: bool,
+    out: &mut String,
+) -> Result<()> {
+    let node = displayable(plan.as_ref())
+        .set_show_schema(verbose)
+        .one_line()
+        .to_string();
+    write!(out, "{}{}", "  ".repeat(depth), node.trim_end())?;
+    if let Some(metrics) = plan.metrics().filter(|_| analyze) {
+        let metrics = metrics.aggregate_by_name();
+        let values = match verbose {
+            true => metrics
+                .sorted_for_display()
+                .timestamps_removed()
+                .iter()
+                .map(|m| m.to_string())
+                .collect::<Vec<_>>(),
+            false => METRICS
+                .iter()
+                // `sum_by_name` skips the builtin metrics such as output_rows
+                .filter_map(|name| {
+                    let value = metrics.sum(|m| m.value().name() == *name)?;
+                    Some(format!("{}={}", name, value))
+                })
+                .collect(),
+        };
+        // operators that never produced a batch record nothing
+        if !values.is_empty() {
+            write!(out, ", metrics=[{}]", values.join(", "))?;
+        }
+    }
+    out.push('\n');
+    for child in plan.children() {
+        physical_tree(child, depth + 1, analyze, verbose, out)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::prelude::ParquetReadOptions;
+
+    async fn fixture(sql: &str, analyze: bool, verbose: bool) -> Result<String> {
+        let ctx = SessionContext::new();
+        let options = ParquetReadOptions::default();
+        ctx.register_parquet("fixture", "assets/fixture.parquet", options)
+            .await?;
+        let df = ctx.sql(sql).await?;
+        Ok(explain(&ctx, df, analyze, verbose).await?.render())
+    }
+
+    /// the lines of a section of the rendered plans
+    fn section<'a>(out: &'a str, title: &str) -> Vec<&'a str> {
+        out.split("\n\n")
+            .find_map(|s| s.strip_prefix(&format!("{}:\n", title)))
+            .unwrap_or_else(|| panic!("no {} in {}", title, out))
+            .lines()
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn explain_should_show_three_indented_plans() -> Result<()> {
+        let out = fixture("select name from fixture where id > 6", false, false).await?;
+        let logical = section(&out, "logical plan");
+        assert_eq!(logical[0], "Projection: fixture.name");
+        assert_eq!(logical[1], "  Filter: fixture.id > Int64(6)");
+        assert_eq!(logical[2], "    TableScan: fixture");
+        let optimized = section(&out, "optimized logical plan");
+        // the filter is pushed into the scan
+        assert!(optimized[2].contains("partial_filters=[fixture.id > Int64(6)]"));
+        let physical = section(&out, "physical plan");
+        assert!(physical[0].starts_with("ProjectionExec"));
+        let scan = physical.last().unwrap();
+        assert!(scan.starts_with("      ParquetExec"), "{}", scan);
+        assert!(scan.contains("pruning_predicate="), "{}", scan);
+        // no metrics without analyze
+        assert!(!out.contains("metrics="), "{}", out);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn analyze_should_show_pruned_row_groups() -> Result<()> {
+        // the first row group has ids 1 to 4
+        let out = fixture("select name from fixture where id > 6", true, false).await?;
+        let physical = section(&out, "physical plan (analyzed)");
+        assert!(physical[0].contains("metrics=[output_rows=2,"), "{}", out);
+        let scan = physical.last().unwrap();
+        assert!(scan.contains("row_groups_pruned_statistics=1"), "{}", scan);
+
+        // within the min/max of both row groups, but in neither bloom filter
+        let out = fixture("select id from fixture where name = 'cat'", true, false).await?;
+        let scan = *section(&out, "physical plan (analyzed)").last().unwrap();
+        assert!(
+            scan.contains("row_groups_pruned_bloom_filter=2"),
+            "{}",
+            scan
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn verbose_should_show_schemas() -> Result<()> {
+        let out = fixture("select name from fixture where id > 6", false, true).await?;
+        let optimized = section(&out, "optimized logical plan");
+        assert!(optimized[0].ends_with("[name:Utf8;N]"), "{}", out);
+        let physical = section(&out, "physical plan");
+        assert!(physical[0].ends_with("schema=[name:Utf8;N]"), "{}", out);
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+//! 展开嵌套列: struct的每个子字段变成一个 `a.b` 形式的叶子列,
+//! 可以限制展开的层数, 超过层数的struct保持原样
+
+use anyhow::Result;
+use arrow::datatypes::DataType;
+use datafusion::prelude::{get_field, ident, DataFrame, Expr};
+
+/// expand struct columns into dotted leaf columns, structs deeper than `depth` are kept
+pub fn flatten(df: DataFrame, depth: usize) -> Result<DataFrame> {
+    let mut expr = vec![];
+    for field in df.schema().fields() {
+        leaves(
+            ident(field.name()),
+            field.name(),
+            field.data_type(),
+            depth,
+            &mut expr,
+        );
+    }
+    Ok(df.select(expr)?)
+}
+
+fn leaves(expr: Expr, name: &str, dt: &DataType, depth: usize, out: &mut Vec<Expr>) {
+    match dt {
+        DataType::Struct(fields) if depth > 0 => {
+            for field in fields {
+                leaves(
+                    get_field(expr.clone(), field.name().as_str()),
+                    &format!("{}.{}", name, field.name()),
+                    field.data_type(),
+                    depth - 1,
+                    out,
+                );
+            }
+        }
+        _ => out.push(expr.alias(name)),
+    }
+}
+
+/// struct, map and union columns can't be described directly, they need to be flattened
+pub(crate) fn is_nested(dt: &DataType) -> bool {
+    matches!(
+        dt,
+        DataType::Struct(_) | DataType::Map(_, _) | DataType::Union(_, _)
+    )
+}
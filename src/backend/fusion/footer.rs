@@ -0,0 +1,442 @@
+//! parquet文件的footer: 物理/逻辑类型, row group, 压缩方式, 每列的统计信息(min/max/null_count)
+//! 以及文件级别的key/value metadata, 不需要扫描数据
+//!
+//! `parquet-meta` 会展开到每个row group里的每个column chunk: 编码, 字典页, bloom filter和page index
+
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use arrow::{
+    array::{Array, ArrayRef, BooleanArray, Int64Array, RecordBatch, StringArray, UInt64Array},
+    compute::{cast as cast_array, sort_to_indices, SortOptions},
+    datatypes::{DataType, Field, Schema},
+    util::display::array_value_to_string,
+};
+use datafusion::datasource::{
+    file_format::parquet::ParquetFormat, listing::ListingTable, TableProvider,
+};
+use parquet::{
+    arrow::{arrow_reader::statistics::StatisticsConverter, parquet_to_arrow_schema},
+    basic::{ConvertedType, Encoding},
+    file::{footer::parse_metadata, metadata::ParquetMetaData},
+};
+use url::Url;
+
+/// metadata of a single parquet file
+#[derive(Debug)]
+pub struct ParquetFooter {
+    pub path: PathBuf,
+    pub metadata: ParquetMetaData,
+}
+
+/// footers of every file of a dataset, shown by `parquet-meta`
+#[derive(Debug)]
+pub struct ParquetMeta(pub Vec<ParquetFooter>);
+
+/// local parquet files behind a registered table, `None` if the table isn't parquet
+pub(crate) fn parquet_files(provider: &dyn TableProvider) -> Result<Option<Vec<PathBuf>>> {
+    let Some(table) = provider.as_any().downcast_ref::<ListingTable>() else {
+        return Ok(None);
+    };
+    if !table.options().format.as_any().is::<ParquetFormat>() {
+        return Ok(None);
+    }
+    let mut files = vec![];
+    for url in table.table_paths() {
+        // only local files can be opened
+        if url.scheme() != "file" {
+            continue;
+        }
+        // the prefix is percent-encoded, spaces or non-ascii names need the decoded path
+        let Ok(path) = AsRef::<Url>::as_ref(url).to_file_path() else {
+            continue;
+        };
+        files.extend(local_files(&path)?);
+    }
+    Ok(Some(files))
+}
+
+/// the file itself, or the parquet files in a directory
+pub(crate) fn local_files(path: &Path) -> Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = std::fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "parquet"))
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+impl ParquetFooter {
+    pub fn try_new(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path).with_context(|| format!("can't open {}", path.display()))?;
+        let metadata = parse_metadata(&file)?;
+        Ok(Self { path, metadata })
+    }
+
+    /// one line overview of the file
+    pub fn summary(&self) -> String {
+        let file = self.metadata.file_metadata();
+        format!(
+            "file: {}\nrows: {}, row groups: {}, format version: {}, created by: {}",
+            self.path.display(),
+            file.num_rows(),
+            self.metadata.num_row_groups(),
+            file.version(),
+            file.created_by().unwrap_or("unknown"),
+        )
+    }
+
+    /// one row per leaf column: parquet types, compression and the footer statistics
+    /// merged over all row groups
+    pub fn columns(&self) -> Result<RecordBatch> {
+        let file = self.metadata.file_metadata();
+        let descr = file.schema_descr();
+        let arrow_schema = parquet_to_arrow_schema(descr, file.key_value_metadata())?;
+        let row_groups = self.metadata.row_groups();
+
+        let (mut names, mut physical, mut logical) = (vec![], vec![], vec![]);
+        let (mut codecs, mut nulls, mut mins, mut maxs) = (vec![], vec![], vec![], vec![]);
+        for (i, column) in descr.columns().iter().enumerate() {
+            let path = column.path();
+            names.push(path.string());
+            physical.push(column.physical_type().to_string());
+            logical.push(match (column.logical_type(), column.converted_type()) {
+                (Some(t), _) => Some(format!("{:?}", t)),
+                (None, ConvertedType::NONE) => None,
+                (None, t) => Some(t.to_string()),
+            });
+            let compression: BTreeSet<String> = row_groups
+                .iter()
+                .map(|rg| rg.column(i).compression().to_string())
+                .collect();
+            codecs.push(compression.into_iter().collect::<Vec<_>>().join(","));
+            // unknown if any row group has no statistics
+            let null_count = row_groups
+                .iter()
+                .map(|rg| rg.column(i).statistics().map(|s| s.null_count()))
+                .sum::<Option<u64>>();
+            nulls.push(null_count);
+
+            // min/max are converted to arrow values, only for top level columns
+            let (min, max) = match path.parts() {
+                [name] => {
+                    let converter = StatisticsConverter::try_new(name, &arrow_schema, descr)?;
+                    (
+                        extreme(&converter.row_group_mins(row_groups.iter())?, false)?,
+                        extreme(&converter.row_group_maxes(row_groups.iter())?, true)?,
+                    )
+                }
+                _ => (None, None),
+            };
+            mins.push(min);
+            maxs.push(max);
+        }
+
+        let schema = Schema::new(vec![
+            Field::new("column", DataType::Utf8, false),
+            Field::new("physical_type", DataType::Utf8, false),
+            Field::new("logical_type", DataType::Utf8, true),
+            Field::new("compression", DataType::Utf8, false),
+            Field::new("null_count", DataType::UInt64, true),
+            Field::new("min", DataType::Utf8, true),
+            Field::new("max", DataType::Utf8, true),
+        ]);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from(names)),
+            Arc::new(StringArray::from(physical)),
+            Arc::new(StringArray::from(logical)),
+            Arc::new(StringArray::from(codecs)),
+            Arc::new(UInt64Array::from(nulls)),
+            Arc::new(StringArray::from(mins)),
+            Arc::new(StringArray::from(maxs)),
+        ];
+        Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+    }
+
+    /// one row per row group: rows and byte sizes
+    pub fn row_groups(&self) -> Result<RecordBatch> {
+        let row_groups = self.metadata.row_groups();
+        let schema = Schema::new(vec![
+            Field::new("row_group", DataType::UInt64, false),
+            Field::new("rows", DataType::Int64, false),
+            Field::new("total_byte_size", DataType::Int64, false),
+            Field::new("compressed_size", DataType::Int64, false),
+        ]);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(UInt64Array::from_iter_values(0..row_groups.len() as u64)),
+            Arc::new(Int64Array::from_iter_values(
+                row_groups.iter().map(|rg| rg.num_rows()),
+            )),
+            Arc::new(Int64Array::from_iter_values(
+                row_groups.iter().map(|rg| rg.total_byte_size()),
+            )),
+            Arc::new(Int64Array::from_iter_values(
+                row_groups.iter().map(|rg| rg.compressed_size()),
+            )),
+        ];
+        Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+    }
+
+    /// one row per column chunk (a column in a row group): encodings, sizes, statistics,
+    /// and whether a dictionary page, bloom filter and page index are written
+    pub fn column_chunks(&self) -> Result<RecordBatch> {
+        let file = self.metadata.file_metadata();
+        let descr = file.schema_descr();
+        let arrow_schema = parquet_to_arrow_schema(descr, file.key_value_metadata())?;
+        let row_groups = self.metadata.row_groups();
+
+        // per row group min/max of the top level columns, indexed by leaf column
+        let mut extremes = vec![];
+        for column in descr.columns().iter() {
+            let extreme = match column.path().parts() {
+                [name] => {
+                    let converter = StatisticsConverter::try_new(name, &arrow_schema, descr)?;
+                    Some((
+                        text_array(converter.row_group_mins(row_groups.iter())?),
+                        text_array(converter.row_group_maxes(row_groups.iter())?),
+                    ))
+                }
+                _ => None,
+            };
+            extremes.push(extreme);
+        }
+
+        let (mut rg_ids, mut names, mut encodings) = (vec![], vec![], vec![]);
+        let (mut compressed, mut uncompressed) = (vec![], vec![]);
+        let (mut nulls, mut distincts, mut mins, mut maxs) = (vec![], vec![], vec![], vec![]);
+        let (mut dictionaries, mut blooms, mut column_indexes, mut offset_indexes) =
+            (vec![], vec![], vec![], vec![]);
+        for (r, rg) in row_groups.iter().enumerate() {
+            for (i, chunk) in rg.columns().iter().enumerate() {
+                rg_ids.push(r as u64);
+                names.push(chunk.column_path().string());
+                let chunk_encodings: Vec<String> =
+                    chunk.encodings().iter().map(|e| e.to_string()).collect();
+                encodings.push(chunk_encodings.join(","));
+                compressed.push(chunk.compressed_size());
+                uncompressed.push(chunk.uncompressed_size());
+                let stats = chunk.statistics();
+                nulls.push(stats.map(|s| s.null_count()));
+                distincts.push(stats.and_then(|s| s.distinct_count()));
+                let (min, max) = match &extremes[i] {
+                    Some((mins, maxs)) => (cell(mins, r)?, cell(maxs, r)?),
+                    None => (None, None),
+                };
+                mins.push(min);
+                maxs.push(max);
+                let dictionary = chunk.dictionary_page_offset().is_some()
+                    || chunk.encodings().iter().any(|e| {
+                        matches!(e, Encoding::PLAIN_DICTIONARY | Encoding::RLE_DICTIONARY)
+                    });
+                dictionaries.push(dictionary);
+                blooms.push(chunk.bloom_filter_offset().is_some());
+                column_indexes.push(chunk.column_index_offset().is_some());
+                offset_indexes.push(chunk.offset_index_offset().is_some());
+            }
+        }
+
+        let schema = Schema::new(vec![
+            Field::new("row_group", DataType::UInt64, false),
+            Field::new("column", DataType::Utf8, false),
+            Field::new("encodings", DataType::Utf8, false),
+            Field::new("compressed_size", DataType::Int64, false),
+            Field::new("uncompressed_size", DataType::Int64, false),
+            Field::new("null_count", DataType::UInt64, true),
+            Field::new("distinct_count", DataType::UInt64, true),
+            Field::new("min", DataType::Utf8, true),
+            Field::new("max", DataType::Utf8, true),
+            Field::new("dictionary", DataType::Boolean, false),
+            Field::new("bloom_filter", DataType::Boolean, false),
+            Field::new("column_index", DataType::Boolean, false),
+            Field::new("offset_index", DataType::Boolean, false),
+        ]);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(UInt64Array::from(rg_ids)),
+            Arc::new(StringArray::from(names)),
+            Arc::new(StringArray::from(encodings)),
+            Arc::new(Int64Array::from(compressed)),
+            Arc::new(Int64Array::from(uncompressed)),
+            Arc::new(UInt64Array::from(nulls)),
+            Arc::new(UInt64Array::from(distincts)),
+            Arc::new(StringArray::from(mins)),
+            Arc::new(StringArray::from(maxs)),
+            Arc::new(BooleanArray::from(dictionaries)),
+            Arc::new(BooleanArray::from(blooms)),
+            Arc::new(BooleanArray::from(column_indexes)),
+            Arc::new(BooleanArray::from(offset_indexes)),
+        ];
+        Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+    }
+
+    /// file level key/value metadata, the serialized arrow schema is too long to show
+    pub fn key_values(&self) -> Result<Option<RecordBatch>> {
+        let Some(kv) = self.metadata.file_metadata().key_value_metadata() else {
+            return Ok(None);
+        };
+        if kv.is_empty() {
+            return Ok(None);
+        }
+        let keys: Vec<&str> = kv.iter().map(|kv| kv.key.as_str()).collect();
+        let values: Vec<Option<String>> = kv
+            .iter()
+            .map(|kv| {
+                kv.value.as_ref().map(|v| match v.chars().count() > 80 {
+                    true => format!(
+                        "{}... ({} bytes)",
+                        v.chars().take(80).collect::<String>(),
+                        v.len()
+                    ),
+                    false => v.clone(),
+                })
+            })
+            .collect();
+        let schema = Schema::new(vec![
+            Field::new("key", DataType::Utf8, false),
+            Field::new("value", DataType::Utf8, true),
+        ]);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from(keys)),
+            Arc::new(StringArray::from(values)),
+        ];
+        Ok(Some(RecordBatch::try_new(Arc::new(schema), columns)?))
+    }
+}
+
+/// smallest (or largest) of the per row group values, binary values are shown as text
+fn extreme(values: &ArrayRef, descending: bool) -> Result<Option<String>> {
+    let options = SortOptions {
+        descending,
+        nulls_first: false,
+    };
+    let indices = sort_to_indices(values, Some(options), Some(1))?;
+    match indices.is_empty() {
+        true => Ok(None),
+        false => cell(&text_array(values.clone()), indices.value(0) as usize),
+    }
+}
+
+/// binary statistics are shown as text if they are valid utf8
+fn text_array(values: ArrayRef) -> ArrayRef {
+    match values.data_type() {
+        DataType::Binary | DataType::LargeBinary => {
+            cast_array(&values, &DataType::Utf8).unwrap_or(values)
+        }
+        _ => values,
+    }
+}
+
+fn cell(values: &ArrayRef, i: usize) -> Result<Option<String>> {
+    Ok(values
+        .is_valid(i)
+        .then(|| array_value_to_string(values, i))
+        .transpose()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 8 rows in 2 row groups, `name` is dictionary encoded with a bloom filter,
+    /// `id` has a page index, `address.city` and `tags` are nested
+    const FIXTURE: &str = "assets/fixture.parquet";
+
+    /// cell of `column` in the row whose first column is `key`
+    fn value(batch: &RecordBatch, key: &str, column: &str) -> Option<String> {
+        let keys = text_array(batch.column(0).clone());
+        let r = (0..batch.num_rows())
+            .find(|r| cell(&keys, *r).unwrap().as_deref() == Some(key))
+            .unwrap();
+        cell(batch.column_by_name(column).unwrap(), r).unwrap()
+    }
+
+    #[test]
+    fn columns_should_show_types_and_merged_statistics() -> Result<()> {
+        let footer = ParquetFooter::try_new(FIXTURE)?;
+        assert!(footer.summary().contains("rows: 8, row groups: 2"));
+        let columns = footer.columns()?;
+        let get = |key: &str, column: &str| value(&columns, key, column);
+        assert_eq!(get("id", "physical_type").unwrap(), "INT64");
+        assert_eq!(get("born", "logical_type").unwrap(), "Date");
+        assert!(get("at", "logical_type")
+            .unwrap()
+            .starts_with("Timestamp { is_adjusted_to_u_t_c: true"));
+        assert_eq!(
+            get("price", "logical_type").unwrap(),
+            "Decimal { scale: 2, precision: 10 }"
+        );
+        // statistics of both row groups are merged
+        assert_eq!(get("name", "null_count").unwrap(), "2");
+        assert_eq!(get("id", "min").unwrap(), "1");
+        assert_eq!(get("id", "max").unwrap(), "8");
+        assert_eq!(get("name", "max").unwrap(), "eve");
+        assert_eq!(get("born", "min").unwrap(), "1997-05-19");
+        assert_eq!(get("price", "max").unwrap(), "11.75");
+        // leaves of nested columns have no min/max
+        assert_eq!(get("address.city", "min"), None);
+        assert_eq!(get("tags.list.item", "max"), None);
+        assert_eq!(get("tags.list.item", "null_count").unwrap(), "3");
+        Ok(())
+    }
+
+    #[test]
+    fn row_groups_should_show_rows_and_sizes() -> Result<()> {
+        let row_groups = ParquetFooter::try_new(FIXTURE)?.row_groups()?;
+        assert_eq!(row_groups.num_rows(), 2);
+        for r in ["0", "1"] {
+            assert_eq!(value(&row_groups, r, "rows").unwrap(), "4");
+            let size: i64 = value(&row_groups, r, "compressed_size").unwrap().parse()?;
+            assert!(size > 0);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn column_chunks_should_show_statistics_and_flags_per_row_group() -> Result<()> {
+        let chunks = ParquetFooter::try_new(FIXTURE)?.column_chunks()?;
+        // 7 leaf columns in each of the 2 row groups
+        assert_eq!(chunks.num_rows(), 14);
+        let get = |row_group: u64, column: &str, key: &str| {
+            let r = (0..chunks.num_rows())
+                .find(|r| {
+                    cell(chunks.column(0), *r).unwrap() == Some(row_group.to_string())
+                        && cell(chunks.column(1), *r).unwrap().as_deref() == Some(column)
+                })
+                .unwrap();
+            cell(chunks.column_by_name(key).unwrap(), r).unwrap()
+        };
+        // statistics of each row group, not of the file
+        assert_eq!(get(0, "id", "min").unwrap(), "1");
+        assert_eq!(get(0, "id", "max").unwrap(), "4");
+        assert_eq!(get(1, "id", "min").unwrap(), "5");
+        assert_eq!(get(1, "name", "max").unwrap(), "eve");
+        assert_eq!(get(1, "name", "null_count").unwrap(), "1");
+        assert_eq!(get(0, "tags.list.item", "null_count").unwrap(), "2");
+        // only `name` is dictionary encoded and has a bloom filter
+        assert_eq!(get(0, "name", "dictionary").unwrap(), "true");
+        assert_eq!(get(0, "name", "bloom_filter").unwrap(), "true");
+        assert!(get(0, "name", "encodings")
+            .unwrap()
+            .contains("RLE_DICTIONARY"));
+        assert_eq!(get(0, "born", "dictionary").unwrap(), "false");
+        assert_eq!(get(0, "born", "bloom_filter").unwrap(), "false");
+        // only `id` has page statistics, every column has an offset index
+        assert_eq!(get(1, "id", "column_index").unwrap(), "true");
+        assert_eq!(get(1, "price", "column_index").unwrap(), "false");
+        assert_eq!(get(1, "price", "offset_index").unwrap(), "true");
+        // leaves of nested columns have no min/max
+        for column in ["address.city", "tags.list.item"] {
+            assert_eq!(get(0, column, "min"), None);
+            assert_eq!(get(1, column, "max"), None);
+        }
+        Ok(())
+    }
+}
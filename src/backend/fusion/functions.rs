@@ -0,0 +1,355 @@
+//! taotie自带的函数: 邮箱域名, json路径取值, user agent解析, 以及解析
+//! `Apr 18, 1990 (29)` 这类日期(juventus.csv的dob列)
+
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, ArrayRef, Date32Array, StringArray, StructArray},
+    datatypes::{DataType, Date32Type, Field, Fields},
+};
+use chrono::{NaiveDate, NaiveDateTime};
+use datafusion::{
+    common::cast::as_string_array,
+    error::Result,
+    logical_expr::{create_udf, ColumnarValue, Volatility},
+    scalar::ScalarValue,
+};
+use serde_json::Value;
+
+use super::plugin::{PluginFunction, UdfPlugin};
+
+/// date formats tried by `parse_date` in order, after dropping a trailing `(...)`
+const DATE_FORMATS: &[&str] = &[
+    "%b %d, %Y",
+    "%B %d, %Y",
+    "%b %d %Y",
+    "%d %b %Y",
+    "%d %B %Y",
+    "%Y-%m-%d",
+    "%Y/%m/%d",
+    "%d.%m.%Y",
+];
+
+const DATETIME_FORMATS: &[&str] = &["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"];
+
+/// browser tokens of a user agent, checked in order since most browsers claim to be
+/// chrome and safari as well
+const BROWSERS: &[(&str, &str)] = &[
+    ("Edg/", "Edge"),
+    ("EdgA/", "Edge"),
+    ("OPR/", "Opera"),
+    ("SamsungBrowser/", "Samsung Internet"),
+    ("FxiOS/", "Firefox"),
+    ("Firefox/", "Firefox"),
+    ("CriOS/", "Chrome"),
+    ("Chrome/", "Chrome"),
+    ("Version/", "Safari"),
+    ("MSIE ", "IE"),
+    ("rv:", "IE"),
+];
+
+const OSES: &[(&str, &str)] = &[
+    ("Windows", "Windows"),
+    ("Android", "Android"),
+    ("iPhone", "iOS"),
+    ("iPad", "iOS"),
+    ("iPod", "iOS"),
+    ("Mac OS X", "macOS"),
+    ("CrOS", "Chrome OS"),
+    ("Linux", "Linux"),
+];
+
+const BOTS: &[&str] = &["bot", "crawler", "spider", "slurp"];
+
+/// the functions shipped with taotie
+#[derive(Debug, Default)]
+pub struct TaotiePlugin;
+
+impl UdfPlugin for TaotiePlugin {
+    fn name(&self) -> &str {
+        "taotie"
+    }
+
+    fn functions(&self) -> Vec<PluginFunction> {
+        vec![
+            PluginFunction::scalar(
+                create_udf(
+                    "email_domain",
+                    vec![DataType::Utf8],
+                    Arc::new(DataType::Utf8),
+                    Volatility::Immutable,
+                    Arc::new(|args| on_arrays(args, email_domain)),
+                ),
+                "email_domain(email)",
+                "Lower-cased domain of an email address, null without an @",
+            ),
+            PluginFunction::scalar(
+                create_udf(
+                    "json_get",
+                    vec![DataType::Utf8, DataType::Utf8],
+                    Arc::new(DataType::Utf8),
+                    Volatility::Immutable,
+                    Arc::new(|args| on_arrays(args, json_get)),
+                ),
+                "json_get(json, '$.a.b[0]')",
+                "Value at a path of a json string, objects and arrays as json",
+            ),
+            PluginFunction::scalar(
+                create_udf(
+                    "ua_parse",
+                    vec![DataType::Utf8],
+                    Arc::new(DataType::Struct(ua_fields())),
+                    Volatility::Immutable,
+                    Arc::new(|args| on_arrays(args, ua_parse)),
+                ),
+                "ua_parse(user_agent)['browser']",
+                "Browser, version, os and device (desktop, mobile, tablet or bot) of a user agent",
+            ),
+            PluginFunction::scalar(
+                create_udf(
+                    "parse_date",
+                    vec![DataType::Utf8],
+                    Arc::new(DataType::Date32),
+                    Volatility::Immutable,
+                    Arc::new(|args| on_arrays(args, parse_date)),
+                ),
+                "parse_date('Apr 18, 1990 (29)')",
+                "Date of common formats such as Apr 18, 1990 or 18.04.1990, null when unknown",
+            ),
+        ]
+    }
+}
+
+/// run `f` on the arguments as arrays, the result is a scalar when every argument is one
+pub(crate) fn on_arrays(
+    args: &[ColumnarValue],
+    f: impl Fn(&[ArrayRef]) -> Result<ArrayRef>,
+) -> Result<ColumnarValue> {
+    let scalar = args
+        .iter()
+        .all(|arg| matches!(arg, ColumnarValue::Scalar(_)));
+    let out = f(&ColumnarValue::values_to_arrays(args)?)?;
+    match scalar {
+        true => Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(&out, 0)?)),
+        false => Ok(ColumnarValue::Array(out)),
+    }
+}
+
+fn email_domain(args: &[ArrayRef]) -> Result<ArrayRef> {
+    let emails = as_string_array(&args[0])?;
+    let domains = emails.iter().map(|email| {
+        let (_, domain) = email?.rsplit_once('@')?;
+        let domain = domain.trim();
+        (!domain.is_empty()).then(|| domain.to_lowercase())
+    });
+    Ok(Arc::new(domains.collect::<StringArray>()))
+}
+
+fn json_get(args: &[ArrayRef]) -> Result<ArrayRef> {
+    let (docs, paths) = (as_string_array(&args[0])?, as_string_array(&args[1])?);
+    let values = docs.iter().zip(paths.iter()).map(|(doc, path)| {
+        let doc: Value = serde_json::from_str(doc?).ok()?;
+        let value = json_path(path?)
+            .iter()
+            .try_fold(&doc, |value, key| match value {
+                Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+                _ => value.get(key),
+            })?;
+        match value {
+            Value::Null => None,
+            Value::String(s) => Some(s.clone()),
+            value => Some(value.to_string()),
+        }
+    });
+    Ok(Arc::new(values.collect::<StringArray>()))
+}
+
+/// keys of a path like `$.a.b[0]`, `a.b[0]` or `a.b.0`
+fn json_path(path: &str) -> Vec<String> {
+    path.trim()
+        .trim_start_matches('$')
+        .replace('[', ".")
+        .replace(']', "")
+        .split('.')
+        .map(|key| key.trim_matches(|c| c == '\'' || c == '"'))
+        .filter(|key| !key.is_empty())
+        .map(|key| key.to_string())
+        .collect()
+}
+
+fn ua_fields() -> Fields {
+    ["browser", "version", "os", "device"]
+        .iter()
+        .map(|name| Field::new(*name, DataType::Utf8, true))
+        .collect()
+}
+
+fn ua_parse(args: &[ArrayRef]) -> Result<ArrayRef> {
+    let agents = as_string_array(&args[0])?;
+    let mut columns: [Vec<Option<String>>; 4] = Default::default();
+    for ua in agents.iter().map(|ua| ua.map(UserAgent::parse)) {
+        columns[0].push(ua.as_ref().and_then(|ua| ua.browser.clone()));
+        columns[1].push(ua.as_ref().and_then(|ua| ua.version.clone()));
+        columns[2].push(ua.as_ref().and_then(|ua| ua.os.clone()));
+        columns[3].push(ua.map(|ua| ua.device.to_string()));
+    }
+    let arrays = columns
+        .into_iter()
+        .map(|values| Arc::new(StringArray::from(values)) as ArrayRef)
+        .collect();
+    let nulls = agents.nulls().cloned();
+    Ok(Arc::new(StructArray::try_new(ua_fields(), arrays, nulls)?))
+}
+
+#[derive(Debug)]
+struct UserAgent {
+    browser: Option<String>,
+    version: Option<String>,
+    os: Option<String>,
+    device: &'static str,
+}
+
+impl UserAgent {
+    fn parse(ua: &str) -> Self {
+        let lower = ua.to_lowercase();
+        let bot = BOTS.iter().any(|b| lower.contains(b));
+        let browser = BROWSERS
+            .iter()
+            .filter(|(_, name)| match *name {
+                "Safari" => ua.contains("Safari"),
+                "IE" => ua.contains("Trident") || ua.contains("MSIE"),
+                _ => true,
+            })
+            .find_map(|(token, name)| {
+                let (_, rest) = ua.split_once(token)?;
+                let version = rest
+                    .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+                    .next()
+                    .filter(|v| !v.is_empty())
+                    .map(|v| v.to_string());
+                Some((name.to_string(), version))
+            });
+        let os = OSES
+            .iter()
+            .find(|(token, _)| ua.contains(token))
+            .map(|(_, name)| name.to_string());
+        let device = if bot {
+            "bot"
+        } else if ua.contains("iPad")
+            || ua.contains("Tablet")
+            || (ua.contains("Android") && !ua.contains("Mobile"))
+        {
+            "tablet"
+        } else if ua.contains("Mobi") || ua.contains("iPhone") || ua.contains("Android") {
+            "mobile"
+        } else {
+            "desktop"
+        };
+        let (browser, version) = match browser {
+            Some((browser, version)) => (Some(browser), version),
+            None => (None, None),
+        };
+        UserAgent {
+            browser,
+            version,
+            os,
+            device,
+        }
+    }
+}
+
+fn parse_date(args: &[ArrayRef]) -> Result<ArrayRef> {
+    let values = as_string_array(&args[0])?;
+    let dates = values
+        .iter()
+        .map(|s| s.and_then(to_date).map(Date32Type::from_naive_date));
+    Ok(Arc::new(dates.collect::<Date32Array>()))
+}
+
+fn to_date(s: &str) -> Option<NaiveDate> {
+    // `Apr 18, 1990 (29)` carries the age in parentheses
+    let s = match s.split_once('(') {
+        Some((date, _)) => date,
+        None => s,
+    }
+    .trim();
+    DATE_FORMATS
+        .iter()
+        .find_map(|fmt| NaiveDate::parse_from_str(s, fmt).ok())
+        .or_else(|| {
+            DATETIME_FORMATS
+                .iter()
+                .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+                .map(|dt| dt.date())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_path_should_split_keys_and_indexes() {
+        let keys = ["a", "b", "0"];
+        assert_eq!(json_path("$.a.b[0]"), keys);
+        assert_eq!(json_path("a.b[0]"), keys);
+        assert_eq!(json_path("a.b.0"), keys);
+        assert_eq!(json_path("$['a'][\"b\"][0]"), keys);
+        assert!(json_path("$").is_empty());
+    }
+
+    #[test]
+    fn to_date_should_read_common_formats() {
+        let date = NaiveDate::from_ymd_opt(1990, 4, 18);
+        for s in [
+            "Apr 18, 1990 (29)",
+            "April 18, 1990",
+            "18 Apr 1990",
+            "1990-04-18",
+            "1990/04/18",
+            "18.04.1990",
+            "1990-04-18 10:30:00",
+            "1990-04-18T10:30:00.123",
+        ] {
+            assert_eq!(to_date(s), date, "{}", s);
+        }
+        assert_eq!(to_date("18/04/1990"), None);
+        assert_eq!(to_date("unknown"), None);
+    }
+
+    #[test]
+    fn user_agent_should_find_browser_os_and_device() {
+        let cases = [
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+                 Chrome/125.0.0.0 Safari/537.36 Edg/125.0.2535.51",
+                (Some("Edge"), Some("125.0.2535.51"), Some("Windows"), "desktop"),
+            ),
+            (
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15 \
+                 (KHTML, like Gecko) Version/17.5 Mobile/15E148 Safari/604.1",
+                (Some("Safari"), Some("17.5"), Some("iOS"), "mobile"),
+            ),
+            (
+                "Mozilla/5.0 (Linux; Android 14; SM-X710) AppleWebKit/537.36 (KHTML, like Gecko) \
+                 Chrome/125.0.0.0 Safari/537.36",
+                (Some("Chrome"), Some("125.0.0.0"), Some("Android"), "tablet"),
+            ),
+            (
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 14.5; rv:126.0) Gecko/20100101 Firefox/126.0",
+                (Some("Firefox"), Some("126.0"), Some("macOS"), "desktop"),
+            ),
+            (
+                "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)",
+                (None, None, None, "bot"),
+            ),
+        ];
+        for (ua, (browser, version, os, device)) in cases {
+            let parsed = UserAgent::parse(ua);
+            assert_eq!(parsed.browser.as_deref(), browser, "{}", ua);
+            assert_eq!(parsed.version.as_deref(), version, "{}", ua);
+            assert_eq!(parsed.os.as_deref(), os, "{}", ua);
+            assert_eq!(parsed.device, device, "{}", ua);
+        }
+    }
+}
@@ -0,0 +1,319 @@
+//! 直方图: 数值列按等宽分桶, 时间列可以按天/周/月分桶,
+//! 在终端里渲染为横向的条形图或者一行sparkline
+
+use anyhow::Result;
+use std::{collections::BTreeMap, sync::Arc};
+
+use arrow::{
+    array::{AsArray, RecordBatch, StringArray},
+    compute::cast as cast_array,
+    datatypes::{DataType, Date32Type, Field, Schema, TimeUnit},
+};
+use chrono::{Days, Months};
+use datafusion::{
+    functions_aggregate::count::count,
+    prelude::{cast, col, date_trunc, floor, ident, lit, when, DataFrame},
+};
+use serde::Serialize;
+
+use super::describe2::{
+    float_value, is_categorical, is_list, render_values, transform, DataFrameDescriber,
+    DescribeMethod,
+};
+use crate::cli::hist::DateBucket;
+
+#[derive(Debug, Serialize)]
+pub struct Bin {
+    pub lower: String,
+    pub upper: String,
+    pub count: u64,
+}
+
+/// a histogram of one column, displayed as a horizontal bar chart
+#[derive(Debug)]
+pub struct Histogram {
+    pub column: String,
+    pub bins: Vec<Bin>,
+    /// max length of a bar in characters
+    pub width: usize,
+}
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// min and max of the described value (length for strings and lists)
+pub(crate) async fn value_range(df: &DataFrame) -> Result<Vec<Option<(f64, f64)>>> {
+    let describer = DataFrameDescriber::try_new(df.clone())?
+        .with_methods(vec![DescribeMethod::Min, DescribeMethod::Max]);
+    let stats = describer.describe().await?;
+    stats
+        .columns()
+        .iter()
+        .skip(1)
+        .map(|array| {
+            let range = float_value(array, 0)?.zip(float_value(array, 1)?);
+            Ok(range)
+        })
+        .collect()
+}
+
+/// equal width histogram over the described value (length for strings and lists)
+pub(crate) async fn histogram(
+    df: &DataFrame,
+    field: &Field,
+    min: f64,
+    max: f64,
+    bins: usize,
+) -> Result<Vec<Bin>> {
+    let bins = if max > min { bins.max(1) } else { 1 };
+    let width = (max - min) / bins as f64;
+    let value = cast(transform(field), DataType::Float64);
+    let x = col("x");
+    // the max value belongs to the last bin
+    let bucket = when(x.clone().gt_eq(lit(max)), lit((bins - 1) as f64))
+        .otherwise(floor((x - lit(min)) / lit(width.max(f64::MIN_POSITIVE))))?;
+    let batches = df
+        .clone()
+        .select(vec![value.alias("x")])?
+        .filter(col("x").is_not_null())?
+        .aggregate(
+            vec![cast(bucket, DataType::Int64).alias("bucket")],
+            vec![count(lit(1)).alias("freq")],
+        )?
+        .collect()
+        .await?;
+    let mut counts = vec![0_u64; bins];
+    for batch in batches.iter() {
+        for r in 0..batch.num_rows() {
+            if let (Some(b), Some(freq)) = (
+                float_value(batch.column(0), r)?,
+                float_value(batch.column(1), r)?,
+            ) {
+                counts[(b.max(0.0) as usize).min(bins - 1)] += freq as u64;
+            }
+        }
+    }
+
+    let bounds: Vec<Option<f64>> = (0..=bins).map(|i| Some(min + width * i as f64)).collect();
+    // bounds of a temporal column are shown as dates
+    let methods = vec![DescribeMethod::Min; bounds.len()];
+    let bounds = match field.data_type().is_temporal() {
+        true => render_values(&methods, bounds, field.data_type())?,
+        false => bounds
+            .into_iter()
+            .map(|b| b.map(|b| format!("{:.2}", b)))
+            .collect(),
+    };
+    let histogram = counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, count)| Bin {
+            lower: bounds[i].clone().unwrap_or_default(),
+            upper: bounds[i + 1].clone().unwrap_or_default(),
+            count,
+        })
+        .collect();
+    Ok(histogram)
+}
+
+/// count of a temporal column per calendar day, week or month, buckets without values
+/// between the first and the last one are kept as zero bins
+pub(crate) async fn date_histogram(
+    df: &DataFrame,
+    field: &Field,
+    bucket: DateBucket,
+) -> Result<Vec<Bin>> {
+    let value = cast(
+        ident(field.name()),
+        DataType::Timestamp(TimeUnit::Microsecond, None),
+    );
+    let batches = df
+        .clone()
+        .select(vec![
+            date_trunc(lit(bucket.to_string()), value).alias("bucket")
+        ])?
+        .filter(col("bucket").is_not_null())?
+        .aggregate(vec![col("bucket")], vec![count(lit(1)).alias("freq")])?
+        .collect()
+        .await?;
+    let mut counts = BTreeMap::new();
+    for batch in batches.iter() {
+        let starts = cast_array(batch.column(0), &DataType::Date32)?;
+        let starts = starts.as_primitive::<Date32Type>();
+        for r in 0..batch.num_rows() {
+            let start = Date32Type::to_naive_date(starts.value(r));
+            let count = float_value(batch.column(1), r)?.unwrap_or_default() as u64;
+            counts.insert(start, count);
+        }
+    }
+    let (Some(first), Some(last)) = (counts.keys().next(), counts.keys().last()) else {
+        return Ok(vec![]);
+    };
+    let mut bins = vec![];
+    let mut start = Some(*first);
+    while let Some(date) = start.filter(|d| d <= last) {
+        bins.push(Bin {
+            lower: date.to_string(),
+            upper: String::new(),
+            count: counts.get(&date).copied().unwrap_or_default(),
+        });
+        start = match bucket {
+            DateBucket::Day => date.checked_add_days(Days::new(1)),
+            DateBucket::Week => date.checked_add_days(Days::new(7)),
+            DateBucket::Month => date.checked_add_months(Months::new(1)),
+        };
+    }
+    Ok(bins)
+}
+
+/// title of the histogram of a column, strings and lists are bucketed by their length
+pub(crate) fn label(field: &Field) -> String {
+    match is_categorical(field.data_type()) || is_list(field.data_type()) {
+        true => format!("length({})", field.name()),
+        false => field.name().to_string(),
+    }
+}
+
+/// one character per bin, the height follows the count
+pub fn sparkline(bins: &[Bin]) -> String {
+    let max = bins.iter().map(|b| b.count).max().unwrap_or(0);
+    bins.iter()
+        .map(|b| match max {
+            0 => SPARKS[0],
+            max => {
+                let level = b.count as f64 / max as f64 * (SPARKS.len() - 1) as f64;
+                SPARKS[level.round() as usize]
+            }
+        })
+        .collect()
+}
+
+/// a sparkline per numeric or temporal column, `None` if there is no such column
+pub(crate) async fn sparklines(df: &DataFrame, bins: usize) -> Result<Option<RecordBatch>> {
+    let fields: Vec<Field> = df
+        .schema()
+        .fields()
+        .iter()
+        .filter(|f| f.data_type().is_numeric() || f.data_type().is_temporal())
+        .map(|f| f.as_ref().clone())
+        .collect();
+    if fields.is_empty() {
+        return Ok(None);
+    }
+    let names: Vec<&str> = fields.iter().map(|f| f.name().as_str()).collect();
+    let df = df.clone().select_columns(&names)?;
+    let ranges = value_range(&df).await?;
+    let mut lines = vec![];
+    for (field, range) in fields.iter().zip(ranges) {
+        let line = match range {
+            Some((min, max)) => sparkline(&histogram(&df, field, min, max, bins).await?),
+            None => String::new(),
+        };
+        lines.push(line);
+    }
+    let schema = Schema::new(vec![
+        Field::new("column", DataType::Utf8, false),
+        Field::new("sparkline", DataType::Utf8, false),
+    ]);
+    let batch = RecordBatch::try_new(
+        Arc::new(schema),
+        vec![
+            Arc::new(StringArray::from(names)),
+            Arc::new(StringArray::from(lines)),
+        ],
+    )?;
+    Ok(Some(batch))
+}
+
+impl Histogram {
+    pub fn render(&self) -> String {
+        let labels: Vec<String> = self
+            .bins
+            .iter()
+            .map(|b| match b.upper.is_empty() {
+                true => b.lower.clone(),
+                false => format!("{} - {}", b.lower, b.upper),
+            })
+            .collect();
+        let label_width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        let max = self.bins.iter().map(|b| b.count).max().unwrap_or(0);
+        let total: u64 = self.bins.iter().map(|b| b.count).sum();
+
+        let mut lines = vec![format!("{} ({} values)", self.column, total)];
+        for (label, bin) in labels.iter().zip(self.bins.iter()) {
+            let len = match max {
+                0 => 0,
+                max => (bin.count as f64 / max as f64 * self.width as f64).round() as usize,
+            };
+            lines.push(format!(
+                "{:>label_width$} | {} {}",
+                label,
+                "█".repeat(len),
+                bin.count
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::Date32Array;
+    use chrono::NaiveDate;
+    use datafusion::prelude::SessionContext;
+
+    fn dates(dates: &[&str]) -> Result<(DataFrame, Field)> {
+        let days = dates.iter().map(|d| {
+            Date32Type::from_naive_date(NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap())
+        });
+        let batch = RecordBatch::try_from_iter([(
+            "at",
+            Arc::new(Date32Array::from_iter_values(days)) as _,
+        )])?;
+        let field = batch.schema().field(0).clone();
+        Ok((SessionContext::new().read_batch(batch)?, field))
+    }
+
+    fn bins(bins: &[Bin]) -> Vec<(&str, u64)> {
+        bins.iter().map(|b| (b.lower.as_str(), b.count)).collect()
+    }
+
+    #[tokio::test]
+    async fn date_histogram_should_keep_empty_weeks() -> Result<()> {
+        // nothing in the week of 2019-05-20
+        let (df, field) = dates(&["2019-05-14", "2019-05-15", "2019-05-28", "2019-06-03"])?;
+        let weeks = date_histogram(&df, &field, DateBucket::Week).await?;
+        assert_eq!(
+            bins(&weeks),
+            [
+                ("2019-05-13", 2),
+                ("2019-05-20", 0),
+                ("2019-05-27", 1),
+                ("2019-06-03", 1)
+            ]
+        );
+        let months = date_histogram(&df, &field, DateBucket::Month).await?;
+        assert_eq!(bins(&months), [("2019-05-01", 3), ("2019-06-01", 1)]);
+        let days = date_histogram(&df, &field, DateBucket::Day).await?;
+        assert_eq!(days.len(), 21);
+        assert_eq!(days.iter().map(|b| b.count).sum::<u64>(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn label_should_mark_lengths() {
+        assert_eq!(
+            label(&Field::new("name", DataType::Utf8, true)),
+            "length(name)"
+        );
+        assert_eq!(
+            label(&Field::new(
+                "tags",
+                DataType::new_list(DataType::Int32, true),
+                true
+            )),
+            "length(tags)"
+        );
+        assert_eq!(label(&Field::new("age", DataType::Int64, true)), "age");
+    }
+}
@@ -0,0 +1,184 @@
+//! session的内存池: 设置了上限时是 `FairSpillPool`, 排序/聚合超出上限会spill到磁盘,
+//! 不能spill的算子直接报错而不是让整个repl被OOM杀掉; 没有上限时不做限制
+//!
+//! 上限可以在运行时用 `set memory_limit` 修改, 同时记录峰值给 `timing` 用
+
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use anyhow::bail;
+use datafusion::{
+    error::Result,
+    execution::memory_pool::{
+        FairSpillPool, MemoryConsumer, MemoryPool, MemoryReservation, UnboundedMemoryPool,
+    },
+};
+
+/// runtime options given at startup
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeSettings {
+    /// bytes, unlimited when `None`
+    pub memory_limit: Option<usize>,
+    /// where sorts and aggregations spill, the os temp dir when `None`
+    pub spill_dir: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct SessionMemoryPool {
+    inner: RwLock<Arc<dyn MemoryPool>>,
+    limit: RwLock<Option<usize>>,
+    peak: AtomicUsize,
+}
+
+impl SessionMemoryPool {
+    pub fn new(limit: Option<usize>) -> Self {
+        Self {
+            inner: RwLock::new(pool(limit)),
+            limit: RwLock::new(limit),
+            peak: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn limit(&self) -> Option<usize> {
+        *self.limit.read().unwrap()
+    }
+
+    /// replace the pool, only between queries since reservations belong to the old one
+    pub fn set_limit(&self, limit: Option<usize>) -> anyhow::Result<()> {
+        let mut inner = self.inner.write().unwrap();
+        if inner.reserved() > 0 {
+            bail!("can't change the memory limit while a query is running");
+        }
+        *inner = pool(limit);
+        *self.limit.write().unwrap() = limit;
+        Ok(())
+    }
+
+    /// the peak since the last reset, restarting from what is reserved now
+    pub fn reset_peak(&self) -> usize {
+        self.peak.swap(self.reserved(), Ordering::Relaxed)
+    }
+
+    fn inner(&self) -> Arc<dyn MemoryPool> {
+        self.inner.read().unwrap().clone()
+    }
+
+    fn record(&self) {
+        self.peak.fetch_max(self.reserved(), Ordering::Relaxed);
+    }
+}
+
+impl MemoryPool for SessionMemoryPool {
+    fn register(&self, consumer: &MemoryConsumer) {
+        self.inner().register(consumer)
+    }
+
+    fn unregister(&self, consumer: &MemoryConsumer) {
+        self.inner().unregister(consumer)
+    }
+
+    fn grow(&self, reservation: &MemoryReservation, additional: usize) {
+        self.inner().grow(reservation, additional);
+        self.record();
+    }
+
+    fn shrink(&self, reservation: &MemoryReservation, shrink: usize) {
+        self.inner().shrink(reservation, shrink)
+    }
+
+    fn try_grow(&self, reservation: &MemoryReservation, additional: usize) -> Result<()> {
+        self.inner().try_grow(reservation, additional)?;
+        self.record();
+        Ok(())
+    }
+
+    fn reserved(&self) -> usize {
+        self.inner().reserved()
+    }
+}
+
+fn pool(limit: Option<usize>) -> Arc<dyn MemoryPool> {
+    match limit {
+        Some(limit) => Arc::new(FairSpillPool::new(limit)),
+        None => Arc::new(UnboundedMemoryPool::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::fusion::DataFusionBackend,
+        cli::{explain::ExplainOpts, set::SetOpts},
+        Backend, ReplDisplay,
+    };
+
+    /// 500k rows in batches of 1000, so the sort can spill between batches
+    const SORT: &str = "select x * 1000 + y as v from unnest(range(0, 500)) as a(x) \
+        cross join unnest(range(0, 1000)) as b(y) order by v desc";
+
+    async fn set(backend: &mut DataFusionBackend, key: &str, value: &str) -> anyhow::Result<()> {
+        backend.set(&SetOpts::new(key.into(), value.into())).await?;
+        Ok(())
+    }
+
+    /// `spilled_bytes` of the sort in the analyzed plan
+    async fn spilled_bytes(backend: &DataFusionBackend) -> anyhow::Result<usize> {
+        let opts = ExplainOpts::new(SORT.into(), true, false);
+        let out = backend.explain(&opts).await?.display().await?;
+        let sort = out.lines().find(|l| l.starts_with("SortExec")).unwrap();
+        let value = sort.split("spilled_bytes=").nth(1).unwrap();
+        Ok(value.trim_end_matches(']').parse()?)
+    }
+
+    #[test]
+    fn pool_should_track_the_peak_and_keep_the_limit_while_reserved() -> anyhow::Result<()> {
+        let pool = Arc::new(SessionMemoryPool::new(Some(1000)));
+        let mut reservation = MemoryConsumer::new("test").register(&(pool.clone() as _));
+        reservation.try_grow(600)?;
+        assert!(reservation.try_grow(600).is_err());
+        assert!(pool.set_limit(Some(2000)).is_err());
+        reservation.free();
+        assert_eq!(pool.reset_peak(), 600);
+        assert_eq!(pool.reset_peak(), 0);
+        pool.set_limit(Some(2000))?;
+        reservation.try_grow(1200)?;
+        assert_eq!(pool.limit(), Some(2000));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sorts_should_spill_over_the_memory_limit() -> anyhow::Result<()> {
+        let spill_dir = std::env::temp_dir().join(format!("taotie-spill-{}", std::process::id()));
+        let settings = RuntimeSettings {
+            memory_limit: Some(2 << 20),
+            spill_dir: Some(spill_dir.clone()),
+        };
+        let mut backend = DataFusionBackend::try_new(settings)?;
+        assert!(spill_dir.is_dir());
+        set(&mut backend, "target_partitions", "1").await?;
+        // the sort reserves 10MB to merge the spilled runs by default
+        set(&mut backend, "sort_spill_reservation_bytes", "262144").await?;
+        assert!(spilled_bytes(&backend).await? > 0);
+
+        // aggregating into a single list can't spill
+        let sql = "select array_agg(x) from unnest(range(0, 500000)) as t(x)";
+        let err = backend.query(sql).await?.collect().await.err().unwrap();
+        assert!(
+            err.to_string().starts_with("Resources exhausted"),
+            "{}",
+            err
+        );
+
+        set(&mut backend, "memory_limit", "unlimited").await?;
+        assert_eq!(spilled_bytes(&backend).await?, 0);
+        assert_eq!(backend.query(sql).await?.count().await?, 1);
+        std::fs::remove_dir_all(&spill_dir)?;
+        Ok(())
+    }
+}
@@ -1,33 +1,506 @@
 pub mod describe;
 pub mod describe2;
+pub mod diff;
+pub mod explain;
+pub mod flatten;
+pub mod footer;
+pub mod functions;
+pub mod hist;
+pub mod memory;
+pub mod moments;
+pub mod output;
+pub mod params;
+pub mod plugin;
+pub mod profile;
+pub mod registry;
+pub mod sample;
+pub mod schema;
+pub mod script;
+pub mod stats;
+pub mod wasm;
 
-use std::ops::Deref;
+use std::{collections::BTreeMap, ops::Deref, sync::Arc, time::Instant};
 
-use arrow::{array::RecordBatch, util::pretty::pretty_format_batches};
-use datafusion::prelude::{
-    CsvReadOptions, DataFrame, NdJsonReadOptions, SessionConfig, SessionContext,
+use anyhow::{anyhow, bail, Context};
+
+use arrow::{
+    array::{RecordBatch, RecordBatchOptions, StringArray},
+    datatypes::{DataType, Field, Schema},
+    util::pretty::pretty_format_batches,
+};
+use clap::ValueEnum;
+use datafusion::{
+    common::{DFSchema, TableReference},
+    datasource::MemTable,
+    execution::{
+        context::SessionState,
+        disk_manager::DiskManagerConfig,
+        runtime_env::{RuntimeConfig, RuntimeEnv},
+    },
+    logical_expr::{JoinType, LogicalPlan},
+    optimizer::{analyzer::AnalyzerRule, Analyzer},
+    prelude::{col, CsvReadOptions, DataFrame, NdJsonReadOptions, SessionConfig, SessionContext},
+    scalar::ScalarValue,
+    sql::parser::Statement,
 };
-use describe2::DataFrameDescriber;
+use describe2::{DataFrameDescriber, DescribeReport};
+use diff::{diff, Diff};
+use explain::{explain, Explain};
+use flatten::flatten;
+use footer::{local_files, parquet_files, ParquetFooter, ParquetMeta};
+use functions::TaotiePlugin;
+use hist::{date_histogram, histogram, sparklines, value_range, Histogram};
+use memory::{RuntimeSettings, SessionMemoryPool};
+use output::{DisplaySettings, Rows};
+use params::{placeholders, positional_count, Params};
+use plugin::{functions_batch, FunctionInfo, Udf, UdfPlugin};
+use profile::{DataFrameProfiler, Profile};
+use registry::{human_bytes, Origin, Registry, Source};
+use sample::{random_seed, sample_fraction, sample_n};
+use schema::{schema_table, schema_tree, SchemaView};
+use script::{header, split_statements, Script};
+use stats::{ExecStats, PlanningStart, RecordingPlanner};
+use wasm::{WasmPlugin, WasmSignature};
 
 use crate::{
     cli::{
+        alias::AliasOpts,
         connect::{ConnectOpts, DatabaseConn},
+        derive::{DeriveOpts, MaterializeOpts},
         describe::DescribeOpts,
+        diff::DiffOpts,
+        drop::DropOpts,
+        explain::ExplainOpts,
+        functions::FunctionsOpts,
         head::HeadOpts,
+        hist::HistOpts,
+        list::ListOpts,
+        params::{ExecuteOpts, LetOpts, PrepareOpts},
+        parquet_meta::ParquetMetaOpts,
+        profile::{ProfileOpts, ProfileOutput},
+        refresh::RefreshOpts,
+        rename::RenameOpts,
         schema::SchemaOpts,
+        set::{parse_size, parse_switch, OutputFormat, SetOpts, TAOTIE_SETTINGS},
+        show::ShowOpts,
         sql::SqlOpts,
+        udf::{UdfAction, UdfOpts},
     },
     Backend, ReplDisplay,
 };
 
-pub struct DataFusionBackend(SessionContext);
+pub struct DataFusionBackend {
+    ctx: SessionContext,
+    registry: Registry,
+    planner: Arc<RecordingPlanner>,
+    pool: Arc<SessionMemoryPool>,
+    display: DisplaySettings,
+    /// session variables set by `let`, bound to `$name`
+    variables: BTreeMap<String, ScalarValue>,
+    /// queries of the prepared statements by name
+    prepared: BTreeMap<String, String>,
+    /// functions registered by plugins
+    functions: Vec<FunctionInfo>,
+}
 
 impl DataFusionBackend {
     pub fn new() -> Self {
+        Self::try_new(RuntimeSettings::default()).expect("Failed to create datafusion runtime")
+    }
+
+    pub fn try_new(settings: RuntimeSettings) -> anyhow::Result<Self> {
         let mut cfg = SessionConfig::new();
         cfg.options_mut().catalog.information_schema = true;
-        let ctx = SessionContext::new_with_config(cfg);
-        DataFusionBackend(ctx)
+        let pool = Arc::new(SessionMemoryPool::new(settings.memory_limit));
+        let disk = match settings.spill_dir {
+            Some(dir) => {
+                std::fs::create_dir_all(&dir)
+                    .with_context(|| format!("can't create spill dir {}", dir.display()))?;
+                DiskManagerConfig::new_specified(vec![dir])
+            }
+            None => DiskManagerConfig::NewOs,
+        };
+        let runtime = RuntimeConfig::new()
+            .with_memory_pool(pool.clone())
+            .with_disk_manager(disk);
+        let runtime = RuntimeEnv::new(runtime)?;
+        let planner = Arc::new(RecordingPlanner::default());
+        let state = SessionState::new_with_config_rt(cfg, Arc::new(runtime));
+        let mut analyzer_rules: Vec<Arc<dyn AnalyzerRule + Send + Sync>> =
+            vec![Arc::new(PlanningStart(planner.clone()))];
+        analyzer_rules.extend(Analyzer::new().rules);
+        let state = state
+            .with_analyzer_rules(analyzer_rules)
+            .with_query_planner(planner.clone());
+        let ctx = SessionContext::new_with_state(state);
+        let mut backend = DataFusionBackend {
+            ctx,
+            registry: Registry::default(),
+            planner,
+            pool,
+            display: DisplaySettings::default(),
+            variables: BTreeMap::new(),
+            prepared: BTreeMap::new(),
+            functions: vec![],
+        };
+        backend.register_plugin(&TaotiePlugin)?;
+        Ok(backend)
+    }
+
+    /// register the functions of a plugin, a name can't be taken twice nor replace a built-in
+    /// function; nothing is registered if one of them is taken
+    pub fn register_plugin(&mut self, plugin: &dyn UdfPlugin) -> anyhow::Result<()> {
+        let functions = plugin.functions();
+        let state = self.ctx.state();
+        for function in functions.iter() {
+            let name = function.udf.name();
+            if let Some(other) = self.functions.iter().find(|f| f.name == name) {
+                bail!(
+                    "function {} of plugin {} is already registered by plugin {}",
+                    name,
+                    plugin.name(),
+                    other.plugin
+                );
+            }
+            if state.scalar_functions().contains_key(name)
+                || state.aggregate_functions().contains_key(name)
+            {
+                bail!(
+                    "function {} of plugin {} would replace the built-in function {}",
+                    name,
+                    plugin.name(),
+                    name
+                );
+            }
+        }
+        for function in functions {
+            self.functions.push(FunctionInfo {
+                name: function.udf.name().to_string(),
+                kind: function.udf.kind(),
+                plugin: plugin.name().to_string(),
+                usage: function.usage,
+                description: function.description,
+            });
+            match function.udf {
+                Udf::Scalar(udf) => self.ctx.register_udf(udf),
+                Udf::Aggregate(udf) => self.ctx.register_udaf(udf),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DataFusionBackend {
+    /// the registered dataset, or an error suggesting the closest registered name
+    async fn dataset(&self, name: &str) -> anyhow::Result<DataFrame> {
+        if self.ctx.table_exist(table_ref(name))? {
+            return Ok(self.ctx.table(table_ref(name)).await?);
+        }
+        let names = self
+            .ctx
+            .catalog("datafusion")
+            .and_then(|catalog| catalog.schema("public"))
+            .map(|schema| schema.table_names())
+            .unwrap_or_default();
+        match closest_name(name, &names) {
+            Some(closest) => bail!("dataset '{}' not found, did you mean '{}'?", name, closest),
+            None => bail!(
+                "dataset '{}' not found, run `.list` to see the datasets",
+                name
+            ),
+        }
+    }
+}
+
+fn last_segment(key: &str) -> String {
+    key.rsplit('.').next().unwrap_or(key).to_string()
+}
+
+/// dataset names are used as is, never parsed as sql: `My Data`, `order` or `用户` are
+/// all valid and case is preserved
+fn table_ref(name: &str) -> TableReference {
+    TableReference::bare(name)
+}
+
+fn closest_name<'a>(name: &str, names: &'a [String]) -> Option<&'a str> {
+    let lower = name.to_lowercase();
+    names
+        .iter()
+        .map(|n| (strsim::levenshtein(&lower, &n.to_lowercase()), n))
+        .filter(|(d, _)| *d <= (name.chars().count() / 3).max(2))
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, n)| n.as_str())
+}
+
+impl DataFusionBackend {
+    /// register a dataset the way `connect` does and remember where it comes from
+    async fn register(&mut self, name: &str, conn: &DatabaseConn) -> anyhow::Result<()> {
+        match conn {
+            DatabaseConn::Postgres(_) => {
+                println!("Postgres is not supported yet");
+                return Ok(());
+            }
+            DatabaseConn::Csv(path) => {
+                let options = CsvReadOptions::new();
+                self.register_csv(name, path, options).await?;
+            }
+            DatabaseConn::Parquet(path) => {
+                self.register_parquet(name, path, Default::default())
+                    .await?;
+            }
+            DatabaseConn::Json(path) => {
+                let ndjson_opts = NdJsonReadOptions::default().file_extension(".ndjson");
+                self.register_json(name, path, ndjson_opts).await?;
+            }
+        }
+        self.registry
+            .insert(name, Source::new(Origin::Connect(conn.clone())));
+        Ok(())
+    }
+
+    /// register the query as a view, or run it and keep the result in memory
+    async fn register_derived(
+        &mut self,
+        name: &str,
+        query: &str,
+        materialize: bool,
+    ) -> anyhow::Result<()> {
+        let df = self.query(query).await?;
+        let origin = match materialize {
+            false => {
+                self.ctx.register_table(table_ref(name), df.into_view())?;
+                Origin::View(query.to_string())
+            }
+            true => {
+                let schema = df.schema().inner().clone();
+                let batches = df.collect().await?;
+                let rows = batches.iter().map(|b| b.num_rows() as u64).sum();
+                let bytes = batches
+                    .iter()
+                    .map(|b| b.get_array_memory_size() as u64)
+                    .sum();
+                let table = MemTable::try_new(schema, vec![batches])?;
+                self.ctx.register_table(table_ref(name), Arc::new(table))?;
+                Origin::Materialized {
+                    query: query.to_string(),
+                    rows,
+                    bytes,
+                }
+            }
+        };
+        self.registry.insert(name, Source::new(origin));
+        Ok(())
+    }
+
+    /// the query planned with the session variables bound, DDL runs right away like `ctx.sql`
+    async fn query(&self, sql: &str) -> anyhow::Result<DataFrame> {
+        self.query_with(sql, None).await
+    }
+
+    /// the query with `$1`, `$2` ... bound as well, for prepared statements
+    async fn query_with(
+        &self,
+        sql: &str,
+        positional: Option<&[ScalarValue]>,
+    ) -> anyhow::Result<DataFrame> {
+        let start = Instant::now();
+        let statement = self.statement(sql)?;
+        let params = Params {
+            variables: &self.variables,
+            positional,
+        };
+        let (statement, values) = params.prepare(statement)?;
+        let plan = self.ctx.state().statement_to_plan(statement).await?;
+        let plan = match (plan, values.is_empty()) {
+            (LogicalPlan::Prepare(prepare), false) => Params {
+                variables: &self.variables,
+                positional: Some(&values),
+            }
+            .bind(Arc::unwrap_or_clone(prepare.input))?,
+            (plan, _) => params.bind(plan)?,
+        };
+        self.planner.record_logical(start.elapsed());
+        Ok(self.ctx.execute_logical_plan(plan).await?)
+    }
+
+    /// the statement of a query, parsed with the dialect of the session
+    fn statement(&self, sql: &str) -> anyhow::Result<Statement> {
+        let state = self.ctx.state();
+        let dialect = &state.config().options().sql_parser.dialect;
+        Ok(state.sql_to_statement(sql, dialect)?)
+    }
+
+    /// the value of a sql expression such as `30`, `'Turin'` or `date '2024-01-01'`, evaluated
+    /// without running a query so it doesn't show up in the timing footer
+    fn scalar(&self, expr: &str) -> anyhow::Result<ScalarValue> {
+        let schema = DFSchema::empty();
+        let parsed = self
+            .ctx
+            .parse_sql_expr(expr, &schema)
+            .with_context(|| format!("invalid value {}, quote strings with ''", expr))?;
+        let params = Params {
+            variables: &self.variables,
+            positional: None,
+        };
+        let physical = self
+            .ctx
+            .create_physical_expr(params.bind_expr(parsed)?.data, &schema)?;
+        let options = RecordBatchOptions::new().with_row_count(Some(1));
+        let row = RecordBatch::try_new_with_options(Arc::new(Schema::empty()), vec![], &options)?;
+        let array = physical.evaluate(&row)?.into_array(1)?;
+        Ok(ScalarValue::try_from_array(&array, 0)?)
+    }
+
+    /// a taotie setting, or the full key of a datafusion option, which can be given without
+    /// its namespace, e.g. `batch_size` or `parquet.pushdown_filters`
+    fn config_key(&self, key: &str) -> anyhow::Result<String> {
+        if TAOTIE_SETTINGS.iter().any(|(k, _)| *k == key) {
+            return Ok(key.to_string());
+        }
+        let keys: Vec<String> = self
+            .ctx
+            .state()
+            .config()
+            .options()
+            .entries()
+            .into_iter()
+            .map(|e| e.key)
+            .collect();
+        let suffix = format!(".{}", key);
+        let matched: Vec<&String> = keys
+            .iter()
+            .filter(|k| *k == key || k.ends_with(&suffix))
+            .collect();
+        match matched.as_slice() {
+            [key] => Ok(key.to_string()),
+            // typos are usually in the last part, e.g. `targt_partitions`
+            [] => match closest_name(
+                key,
+                &keys.iter().map(|k| last_segment(k)).collect::<Vec<_>>(),
+            ) {
+                Some(closest) => bail!("unknown setting '{}', did you mean '{}'?", key, closest),
+                None => bail!("unknown setting '{}', run `.show` to see the settings", key),
+            },
+            keys => bail!(
+                "setting '{}' is ambiguous: {}",
+                key,
+                keys.iter()
+                    .map(|k| k.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    /// value and description of a setting by its full key
+    fn setting(&self, key: &str) -> anyhow::Result<(String, String)> {
+        let value = match key {
+            "format" => self.display.format.to_string(),
+            "max_rows" => match self.display.max_rows {
+                Some(n) => n.to_string(),
+                None => "unlimited".to_string(),
+            },
+            "timing" => match self.planner.is_enabled() {
+                true => "on".to_string(),
+                false => "off".to_string(),
+            },
+            "memory_limit" => match self.pool.limit() {
+                Some(limit) => human_bytes(limit as u64),
+                None => "unlimited".to_string(),
+            },
+            key => {
+                let entry = self
+                    .ctx
+                    .state()
+                    .config()
+                    .options()
+                    .entries()
+                    .into_iter()
+                    .find(|e| e.key == key)
+                    .ok_or_else(|| anyhow!("unknown setting '{}'", key))?;
+                return Ok((
+                    entry.value.unwrap_or_else(|| "NULL".to_string()),
+                    entry.description.to_string(),
+                ));
+            }
+        };
+        let description = TAOTIE_SETTINGS
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, d)| d.to_string())
+            .unwrap_or_default();
+        Ok((value, description))
+    }
+
+    /// register a dataset again the way it was, the schema and file list are inferred again,
+    /// derived datasets run their query again
+    async fn reregister(&mut self, name: &str, source: &Source) -> anyhow::Result<()> {
+        let old = self.ctx.deregister_table(table_ref(name))?;
+        let res = match &source.origin {
+            Origin::Connect(conn) => self.register(name, conn).await,
+            Origin::View(query) => self.register_derived(name, query, false).await,
+            Origin::Materialized { query, .. } => self.register_derived(name, query, true).await,
+        };
+        // e.g. the file is gone, keep the dataset as it was
+        if let (Err(_), Some(old)) = (&res, old) {
+            self.ctx.register_table(table_ref(name), old)?;
+        }
+        res
+    }
+
+    /// parquet datasets take the parquet options of the session when they are registered,
+    /// so they are registered again after a change, returns their names
+    async fn reregister_parquet(&mut self) -> anyhow::Result<Vec<String>> {
+        let mut names = self
+            .registry
+            .iter()
+            .filter(|(_, source)| {
+                matches!(source.origin, Origin::Connect(DatabaseConn::Parquet(_)))
+            })
+            .map(|(name, source)| (name.clone(), source.clone()))
+            .collect::<Vec<_>>();
+        names.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, source) in names.iter() {
+            self.reregister(name, source).await?;
+        }
+        Ok(names.into_iter().map(|(name, _)| name).collect())
+    }
+
+    /// derived datasets whose query reads `name`, they would break if it's dropped or renamed
+    fn dependents(&self, name: &str) -> anyhow::Result<Vec<String>> {
+        let state = self.ctx.state();
+        let mut dependents = vec![];
+        for (dependent, source) in self.registry.iter() {
+            let Some(query) = source.query() else {
+                continue;
+            };
+            let tables = state.resolve_table_references(&self.statement(query)?)?;
+            if tables.iter().any(|table| table.table() == name) {
+                dependents.push(dependent.clone());
+            }
+        }
+        dependents.sort();
+        Ok(dependents)
+    }
+
+    fn ensure_unused(&self, name: &str, action: &str) -> anyhow::Result<()> {
+        let dependents = self.dependents(name)?;
+        if !dependents.is_empty() {
+            bail!(
+                "can't {} '{}', it is used by {}, drop them first",
+                action,
+                name,
+                dependents.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    fn ensure_new(&self, name: &str) -> anyhow::Result<()> {
+        if self.ctx.table_exist(table_ref(name))? {
+            bail!("dataset '{}' already exists", name);
+        }
+        Ok(())
     }
 }
 
@@ -41,13 +514,17 @@ impl Deref for DataFusionBackend {
     type Target = SessionContext;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.ctx
     }
 }
 impl ReplDisplay for DataFrame {
     async fn display(&self) -> anyhow::Result<String> {
         // datafusion::dataframe::DataFrame::show(self.clone()).await?;
-        let rows = self.clone().collect().await?;
+        let mut rows = self.clone().collect().await?;
+        // keep the header when nothing matches
+        if rows.is_empty() {
+            rows.push(RecordBatch::new_empty(self.schema().inner().clone()));
+        }
         let data = pretty_format_batches(&rows)?;
         Ok(data.to_string())
     }
@@ -55,71 +532,1082 @@ impl ReplDisplay for DataFrame {
 
 impl ReplDisplay for RecordBatch {
     async fn display(&self) -> anyhow::Result<String> {
-        let data = pretty_format_batches(&[self.clone()])?;
+        let data = pretty_format_batches(std::slice::from_ref(self))?;
         Ok(data.to_string())
     }
 }
 
+impl ReplDisplay for DescribeReport {
+    async fn display(&self) -> anyhow::Result<String> {
+        let mut data = self.stats.display().await?;
+        if let Some(categorical) = &self.categorical {
+            data.push_str("\ncategorical:\n");
+            data.push_str(&categorical.display().await?);
+        }
+        if let Some(lists) = &self.lists {
+            data.push_str("\nlist elements:\n");
+            data.push_str(&lists.display().await?);
+        }
+        if let Some(sparklines) = &self.sparklines {
+            data.push_str("\ndistribution:\n");
+            data.push_str(&sparklines.display().await?);
+        }
+        Ok(data)
+    }
+}
+
+impl ReplDisplay for SchemaView {
+    async fn display(&self) -> anyhow::Result<String> {
+        match self {
+            SchemaView::Table(batch) => batch.display().await,
+            SchemaView::Tree(tree) => Ok(tree.clone()),
+            SchemaView::Parquet(batch, footers) => {
+                let mut data = batch.display().await?;
+                for footer in footers {
+                    data.push_str(&format!("\n{}\n", footer.summary()));
+                    data.push_str(&footer.columns()?.display().await?);
+                    if let Some(kv) = footer.key_values()? {
+                        data.push_str("\nkey/value metadata:\n");
+                        data.push_str(&kv.display().await?);
+                    }
+                }
+                Ok(data)
+            }
+        }
+    }
+}
+
+impl ReplDisplay for ParquetMeta {
+    async fn display(&self) -> anyhow::Result<String> {
+        let mut data = vec![];
+        for footer in self.0.iter() {
+            data.push(footer.summary());
+            data.push(footer.row_groups()?.display().await?);
+            data.push(footer.column_chunks()?.display().await?);
+        }
+        Ok(data.join("\n"))
+    }
+}
+
+impl ReplDisplay for Rows {
+    async fn display(&self) -> anyhow::Result<String> {
+        self.render().await
+    }
+}
+
+impl ReplDisplay for Script {
+    async fn display(&self) -> anyhow::Result<String> {
+        self.render().await
+    }
+}
+
+impl ReplDisplay for Explain {
+    async fn display(&self) -> anyhow::Result<String> {
+        Ok(self.render())
+    }
+}
+
+impl ReplDisplay for Diff {
+    async fn display(&self) -> anyhow::Result<String> {
+        self.render()
+    }
+}
+
+impl ReplDisplay for Histogram {
+    async fn display(&self) -> anyhow::Result<String> {
+        Ok(self.render())
+    }
+}
+
+impl ReplDisplay for Profile {
+    async fn display(&self) -> anyhow::Result<String> {
+        self.summary()?.display().await
+    }
+}
+
 impl Backend for DataFusionBackend {
     // type DataFrame = DataFrame;
-    async fn connect(&mut self, opts: &ConnectOpts) -> anyhow::Result<()> {
-        println!("Connect to dataset: {:?}", opts);
-        match &opts.conn {
-            DatabaseConn::Postgres(_) => {
-                println!("Postgres is not supported yet");
+    fn timing(&mut self, on: Option<bool>) -> bool {
+        let on = on.unwrap_or(!self.planner.is_enabled());
+        self.planner.set_enabled(on);
+        on
+    }
+
+    fn timing_start(&self) -> Instant {
+        self.planner.take();
+        self.pool.reset_peak();
+        Instant::now()
+    }
+
+    fn timing_footer(&self, start: Instant) -> Option<String> {
+        if !self.planner.is_enabled() {
+            return None;
+        }
+        let wall = start.elapsed();
+        let (plans, logical) = self.planner.take();
+        let stats = ExecStats::new(&plans, logical, wall, self.pool.reset_peak());
+        Some(stats.render())
+    }
+
+    async fn set(&mut self, opts: &SetOpts) -> anyhow::Result<String> {
+        let value = opts.value.as_str();
+        let key = match opts.key.as_str() {
+            "format" => {
+                self.display.format = OutputFormat::from_str(value, true)
+                    .map_err(|_| anyhow!("invalid format '{}', use table, csv or json", value))?;
+                "format".to_string()
             }
-            DatabaseConn::Csv(path) => {
-                let options = CsvReadOptions::new();
-                self.register_csv(&opts.name, path, options).await?;
+            "max_rows" => {
+                self.display.max_rows = match value {
+                    "unlimited" | "0" => None,
+                    n => Some(n.parse().map_err(|_| {
+                        anyhow!("invalid max_rows '{}', use a number or unlimited", n)
+                    })?),
+                };
+                "max_rows".to_string()
             }
-            DatabaseConn::Parquet(path) => {
-                self.register_parquet(&opts.name, path, Default::default())
-                    .await?;
+            "timing" => {
+                self.timing(Some(parse_switch(value)?));
+                "timing".to_string()
             }
-            DatabaseConn::Json(path) => {
-                let ndjson_opts = NdJsonReadOptions::default().file_extension(".ndjson");
-                self.register_json(&opts.name, path, ndjson_opts).await?;
+            "memory_limit" => {
+                self.pool.set_limit(parse_size(value)?)?;
+                "memory_limit".to_string()
+            }
+            key => {
+                let key = self.config_key(key)?;
+                self.ctx
+                    .state_ref()
+                    .write()
+                    .config_mut()
+                    .options_mut()
+                    .set(&key, value)?;
+                key
+            }
+        };
+        let mut msg = format!("{} = {}", key, self.setting(&key)?.0);
+        if key.starts_with("datafusion.execution.parquet.") {
+            let names = self.reregister_parquet().await?;
+            if !names.is_empty() {
+                msg.push_str(&format!(
+                    "\nregistered {} again with the new option, `.refresh` the datasets derived from them",
+                    names.join(", ")
+                ));
             }
         }
+        Ok(msg)
+    }
+
+    async fn show(&self, opts: &ShowOpts) -> anyhow::Result<impl ReplDisplay> {
+        let keys = match &opts.key {
+            Some(key) => vec![self.config_key(key)?],
+            None => TAOTIE_SETTINGS
+                .iter()
+                .map(|(key, _)| key.to_string())
+                .chain(
+                    self.ctx
+                        .state()
+                        .config()
+                        .options()
+                        .entries()
+                        .into_iter()
+                        .map(|e| e.key),
+                )
+                .collect(),
+        };
+        let mut values = vec![];
+        let mut descriptions = vec![];
+        for key in keys.iter() {
+            let (value, description) = self.setting(key)?;
+            values.push(value);
+            descriptions.push(description);
+        }
+        let schema = Schema::new(vec![
+            Field::new("name", DataType::Utf8, false),
+            Field::new("value", DataType::Utf8, false),
+            Field::new("description", DataType::Utf8, false),
+        ]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(StringArray::from(keys)),
+                Arc::new(StringArray::from(values)),
+                Arc::new(StringArray::from(descriptions)),
+            ],
+        )?;
+        Ok(batch)
+    }
+
+    async fn connect(&mut self, opts: &ConnectOpts) -> anyhow::Result<()> {
+        println!("Connect to dataset: {:?}", opts);
+        self.register(&opts.dataset_name(), &opts.conn).await
+    }
+
+    async fn drop(&mut self, opts: &DropOpts) -> anyhow::Result<()> {
+        self.dataset(&opts.name).await?;
+        self.ensure_unused(&opts.name, "drop")?;
+        self.ctx.deregister_table(table_ref(&opts.name))?;
+        self.registry.remove(&opts.name);
+        Ok(())
+    }
+
+    async fn rename(&mut self, opts: &RenameOpts) -> anyhow::Result<()> {
+        self.dataset(&opts.name).await?;
+        self.ensure_unused(&opts.name, "rename")?;
+        self.ensure_new(&opts.new_name)?;
+        if let Some(provider) = self.ctx.deregister_table(table_ref(&opts.name))? {
+            self.ctx
+                .register_table(table_ref(&opts.new_name), provider)?;
+        }
+        if let Some(source) = self.registry.remove(&opts.name) {
+            self.registry.insert(&opts.new_name, source);
+        }
         Ok(())
     }
 
-    async fn list(&self) -> anyhow::Result<impl ReplDisplay> {
+    async fn alias(&mut self, opts: &AliasOpts) -> anyhow::Result<()> {
+        self.dataset(&opts.name).await?;
+        self.ensure_new(&opts.alias)?;
+        // a view rather than the same provider, `list` shows what it stands for and `refresh`
+        // plans it again
+        let query = format!("select * from {}", table_ref(&opts.name).to_quoted_string());
+        self.register_derived(&opts.alias, &query, false).await
+    }
+
+    async fn refresh(&mut self, opts: &RefreshOpts) -> anyhow::Result<()> {
+        self.dataset(&opts.name).await?;
+        let Some(source) = self.registry.get(&opts.name).cloned() else {
+            bail!(
+                "dataset '{}' wasn't registered by taotie, can't refresh it",
+                opts.name
+            );
+        };
+        self.reregister(&opts.name, &source).await
+    }
+
+    async fn derive(&mut self, opts: &DeriveOpts) -> anyhow::Result<()> {
+        self.ensure_new(&opts.name)?;
+        self.register_derived(&opts.name, &opts.query, false).await
+    }
+
+    async fn materialize(&mut self, opts: &MaterializeOpts) -> anyhow::Result<()> {
+        self.ensure_new(&opts.name)?;
+        self.register_derived(&opts.name, &opts.query, true).await
+    }
+
+    async fn list(&self, opts: &ListOpts) -> anyhow::Result<impl ReplDisplay> {
         let sql = "select table_name, table_type from information_schema.tables where table_schema = 'public'";
-        let df = self.0.sql(sql).await?;
+        let sources = self.ctx.read_batch(self.registry.to_record_batch()?)?;
+        let columns: &[&str] = match opts.verbose {
+            true => &[
+                "table_name",
+                "table_type",
+                "source",
+                "format",
+                "derived_from",
+                "estimated_rows",
+                "memory",
+                "registered_at",
+            ],
+            false => &["table_name", "table_type", "derived_from", "memory"],
+        };
+        let df = self
+            .ctx
+            .sql(sql)
+            .await?
+            .join(sources, JoinType::Left, &["table_name"], &["name"], None)?
+            .select_columns(columns)?
+            .sort(vec![col("table_name").sort(true, false)])?;
         Ok(df)
     }
 
     async fn describe(&self, opts: &DescribeOpts) -> anyhow::Result<impl ReplDisplay> {
-        let df = self.0.sql(&format!("select * from {}", opts.name)).await?;
+        let mut df = self.dataset(&opts.name).await?;
+        if let Some(depth) = opts.flatten {
+            df = flatten(df, depth)?;
+        }
+        if let Some(columns) = &opts.columns {
+            let mut columns: Vec<&str> = columns.iter().map(|c| c.as_str()).collect();
+            // group columns are always needed
+            for by in opts.by.iter() {
+                if !columns.contains(&by.as_str()) {
+                    columns.push(by);
+                }
+            }
+            df = df.select_columns(&columns)?;
+        }
         // datafusion使用的
         // let df = df.describe().await?;
         // 我们抄的datafusion的
         // let df1 = DescribeDataFrame::new(df.clone());
         // let batchs = df1.to_record_batch().await.unwrap();
         // print!("{}",pretty_format_batches(&[batchs])?);
-        let df_describer = DataFrameDescriber::try_new(df)?;
-        let df = df_describer.describe().await.unwrap();
-        let df = df_describer.cast_back(df);
-        // df.display().await?;
-        Ok(df)
+        let df_describer = DataFrameDescriber::try_new(df.clone())?.with_methods(opts.methods());
+        if !opts.by.is_empty() {
+            let df_describer = df_describer.with_group_by(opts.by.clone())?;
+            let stats = df_describer.describe_by().await?;
+            return Ok(DescribeReport {
+                stats,
+                categorical: None,
+                lists: None,
+                sparklines: None,
+            });
+        }
+        let mut report = df_describer.report(opts.top_k, opts.lists).await?;
+        if opts.sparkline {
+            report.sparklines = sparklines(&df, 20).await?;
+        }
+        Ok(report)
     }
     async fn schema(&self, opts: &SchemaOpts) -> anyhow::Result<impl ReplDisplay> {
-        let df = self.0.sql(&format!("DESCRIBE {}", opts.name)).await?;
-        Ok(df)
+        let mut df = self.dataset(&opts.name).await?;
+        if let Some(depth) = opts.flatten {
+            df = flatten(df, depth)?;
+        }
+        let schema = df.schema().as_arrow();
+        if opts.tree {
+            return Ok(SchemaView::Tree(schema_tree(&opts.name, schema)));
+        }
+        let table = schema_table(schema)?;
+        let provider = self.ctx.table_provider(table_ref(&opts.name)).await?;
+        let view = match parquet_files(provider.as_ref())? {
+            Some(files) => {
+                let footers = files
+                    .iter()
+                    .map(ParquetFooter::try_new)
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                SchemaView::Parquet(table, footers)
+            }
+            None => SchemaView::Table(table),
+        };
+        Ok(view)
     }
 
     async fn head(&self, opts: &HeadOpts) -> anyhow::Result<impl ReplDisplay> {
-        let n = opts.n.unwrap_or(5);
-        let df = self
-            .0
-            .sql(&format!("SELECT * FROM {} LIMIT {}", opts.name, n))
-            .await?;
-        Ok(df)
+        let mut df = self.dataset(&opts.name).await?;
+        if let Some(filter) = &opts.filter {
+            let expr = df.parse_sql_expr(filter)?;
+            df = df.filter(expr)?;
+        }
+        let seed = opts.seed.unwrap_or_else(random_seed);
+        let df = match (opts.sample, opts.sample_n) {
+            (Some(fraction), _) => {
+                sample_fraction(df, fraction, seed)?.limit(opts.offset, opts.n)?
+            }
+            (None, Some(n)) => sample_n(df, n, seed)?.limit(opts.offset, None)?,
+            (None, None) if opts.tail => {
+                let n = opts.n.unwrap_or(5);
+                // rows before the skipped ones at the end
+                let rows = df.clone().count().await?.saturating_sub(opts.offset);
+                df.limit(rows.saturating_sub(n), Some(n.min(rows)))?
+            }
+            (None, None) => df.limit(opts.offset, Some(opts.n.unwrap_or(5)))?,
+        };
+        let df = match &opts.columns {
+            Some(columns) => {
+                df.select_columns(&columns.iter().map(|c| c.as_str()).collect::<Vec<_>>())?
+            }
+            None => df,
+        };
+        Ok(Rows::new(df, &self.display))
+    }
+
+    async fn hist(&self, opts: &HistOpts) -> anyhow::Result<impl ReplDisplay> {
+        let df = self.dataset(&opts.name).await?;
+        // leaves of structs can be addressed by their dotted name
+        let df = flatten(df, usize::MAX)?.select_columns(&[opts.column.as_str()])?;
+        let field = df.schema().field(0).clone();
+        let bins = match opts.unit {
+            Some(bucket) if field.data_type().is_temporal() => {
+                date_histogram(&df, &field, bucket).await?
+            }
+            Some(_) => anyhow::bail!("--unit only works on temporal columns"),
+            None => match value_range(&df).await?[0] {
+                Some((min, max)) => histogram(&df, &field, min, max, opts.bins).await?,
+                None => vec![],
+            },
+        };
+        Ok(Histogram {
+            column: hist::label(&field),
+            bins,
+            width: opts.width,
+        })
+    }
+
+    async fn profile(&self, opts: &ProfileOpts) -> anyhow::Result<impl ReplDisplay> {
+        let df = self.dataset(&opts.name).await?;
+        // every leaf of a struct is profiled as a column
+        let df = flatten(df, usize::MAX)?;
+        let profiler = DataFrameProfiler::new(&opts.name, df, opts.bins, opts.top_k);
+        let profile = profiler.profile().await?;
+        match &opts.out {
+            ProfileOutput::Html(path) => std::fs::write(path, profile.to_html())?,
+            ProfileOutput::Json(path) => std::fs::write(path, profile.to_json()?)?,
+        }
+        Ok(profile)
+    }
+
+    async fn diff(&self, opts: &DiffOpts) -> anyhow::Result<impl ReplDisplay> {
+        let a = self.dataset(&opts.a).await?;
+        let b = self.dataset(&opts.b).await?;
+        diff((&opts.a, a), (&opts.b, b), &opts.key, opts.limit).await
+    }
+
+    async fn parquet_meta(&self, opts: &ParquetMetaOpts) -> anyhow::Result<impl ReplDisplay> {
+        // a registered dataset wins over a path with the same name
+        let files = match self.ctx.table_exist(table_ref(&opts.target))? {
+            true => {
+                let provider = self.ctx.table_provider(table_ref(&opts.target)).await?;
+                parquet_files(provider.as_ref())?
+                    .ok_or_else(|| anyhow::anyhow!("{} is not a parquet dataset", opts.target))?
+            }
+            false => local_files(std::path::Path::new(&opts.target))?,
+        };
+        if files.is_empty() {
+            anyhow::bail!("no parquet file found in {}", opts.target);
+        }
+        let footers = files
+            .iter()
+            .map(ParquetFooter::try_new)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(ParquetMeta(footers))
     }
 
     async fn sql(&self, opts: &SqlOpts) -> anyhow::Result<impl ReplDisplay> {
-        let df = self.0.sql(&opts.query).await?;
-        Ok(df)
+        let mut statements = split_statements(&opts.text()?)?;
+        let count = statements.len();
+        let Some(last) = statements.pop() else {
+            bail!("no sql statement found");
+        };
+        let failed = |i: usize| format!("statement {} of {} failed", i, count);
+        let mut outputs = vec![];
+        // every statement but the last runs now, so DDL and inserts are seen by the next one,
+        // `query` itself executes DDL such as `CREATE VIEW` right away
+        for (i, statement) in statements.iter().enumerate() {
+            let df = self.query(statement).await.with_context(|| failed(i + 1))?;
+            if opts.all {
+                let rows = Rows::new(df, &self.display).render().await;
+                let output = rows.with_context(|| failed(i + 1))?;
+                outputs.push(format!("{}\n{}", header(statement), output));
+            } else {
+                df.collect().await.with_context(|| failed(i + 1))?;
+            }
+        }
+        let df = self.query(&last).await.with_context(|| failed(count))?;
+        Ok(Script {
+            outputs,
+            header: opts.all.then(|| header(&last)),
+            last: Rows::new(df, &self.display),
+            statements: count,
+        })
+    }
+
+    async fn explain(&self, opts: &ExplainOpts) -> anyhow::Result<impl ReplDisplay> {
+        let df = self.query(&opts.query).await?;
+        explain(&self.ctx, df, opts.analyze, opts.verbose).await
+    }
+
+    async fn functions(&self, _opts: &FunctionsOpts) -> anyhow::Result<impl ReplDisplay> {
+        functions_batch(&self.functions)
+    }
+
+    async fn udf(&mut self, opts: &UdfOpts) -> anyhow::Result<String> {
+        let signature = match &opts.name {
+            Some(name) => Some(WasmSignature {
+                name: name.clone(),
+                export: opts.export.clone().unwrap_or_else(|| name.clone()),
+                args: opts.args.clone(),
+                returns: opts.returns.context("--returns is required with --name")?,
+            }),
+            None => None,
+        };
+        let plugin = match opts.action {
+            UdfAction::Load => WasmPlugin::load(&opts.path, signature)?,
+        };
+        let usages = plugin
+            .functions()
+            .into_iter()
+            .map(|f| f.usage)
+            .collect::<Vec<_>>();
+        self.register_plugin(&plugin)?;
+        Ok(format!(
+            "loaded {} function(s) from {}: {}",
+            usages.len(),
+            plugin.name(),
+            usages.join(", ")
+        ))
+    }
+
+    async fn define(&mut self, opts: &LetOpts) -> anyhow::Result<String> {
+        let name = opts.name.clone().context("a variable name is required")?;
+        let value = self.scalar(&opts.expr())?;
+        let msg = format!("${} = {} ({})", name, value, value.data_type());
+        self.variables.insert(name, value);
+        Ok(msg)
+    }
+
+    async fn variables(&self) -> anyhow::Result<impl ReplDisplay> {
+        let schema = Schema::new(vec![
+            Field::new("name", DataType::Utf8, false),
+            Field::new("type", DataType::Utf8, false),
+            Field::new("value", DataType::Utf8, false),
+        ]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(StringArray::from_iter_values(
+                    self.variables.keys().map(|name| format!("${}", name)),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    self.variables.values().map(|v| v.data_type().to_string()),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    self.variables.values().map(|v| v.to_string()),
+                )),
+            ],
+        )?;
+        Ok(batch)
+    }
+
+    async fn prepare(&mut self, opts: &PrepareOpts) -> anyhow::Result<String> {
+        // parsed only, the types of the parameters are known when `execute` plans it
+        let ids = placeholders(&self.statement(&opts.query)?);
+        self.prepared.insert(opts.name.clone(), opts.query.clone());
+        match ids.is_empty() {
+            true => Ok(format!("prepared {} without parameters", opts.name)),
+            false => Ok(format!(
+                "prepared {} with parameters {}",
+                opts.name,
+                ids.into_iter().collect::<Vec<_>>().join(", ")
+            )),
+        }
+    }
+
+    async fn execute(&self, opts: &ExecuteOpts) -> anyhow::Result<impl ReplDisplay> {
+        let Some(query) = self.prepared.get(&opts.name) else {
+            bail!(
+                "prepared statement '{}' not found, create it with `.prepare {} as \"...\"`",
+                opts.name,
+                opts.name
+            );
+        };
+        let expected = positional_count(&placeholders(&self.statement(query)?));
+        if opts.params.len() != expected {
+            bail!(
+                "{} expects {} parameter(s), got {}",
+                opts.name,
+                expected,
+                opts.params.len()
+            );
+        }
+        let values = opts
+            .params
+            .iter()
+            .map(|param| self.scalar(param))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let df = self.query_with(query, Some(&values)).await?;
+        Ok(Rows::new(df, &self.display))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::udf::WasmType;
+
+    /// sql keywords, spaces and non-ascii names
+    const NAMES: [&str; 4] = ["order", "user", "My Data", "用户"];
+
+    #[test]
+    fn table_ref_should_keep_names_as_is() {
+        for name in NAMES {
+            let table = table_ref(name);
+            assert_eq!(table.table(), name);
+            assert_eq!(table.schema(), None);
+        }
+        assert_eq!(table_ref("My Data").to_quoted_string(), r#""My Data""#);
+    }
+
+    #[tokio::test]
+    async fn dataset_should_find_any_name() -> anyhow::Result<()> {
+        let mut backend = DataFusionBackend::new();
+        for name in NAMES {
+            let conn = DatabaseConn::Csv("assets/juventus.csv".to_string());
+            backend.register(name, &conn).await?;
+            assert_eq!(backend.dataset(name).await?.count().await?, 27);
+            let sql = format!("select * from {}", table_ref(name).to_quoted_string());
+            assert_eq!(backend.query(&sql).await?.count().await?, 27);
+        }
+        let err = backend.dataset("my data").await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "dataset 'my data' not found, did you mean 'My Data'?"
+        );
+        Ok(())
+    }
+
+    async fn juventus() -> anyhow::Result<DataFusionBackend> {
+        let mut backend = DataFusionBackend::new();
+        let conn = DatabaseConn::Csv("assets/juventus.csv".to_string());
+        backend.register("juventus", &conn).await?;
+        backend
+            .define(&LetOpts::new(
+                Some("pattern".into()),
+                vec!["'Centre%'".into()],
+            ))
+            .await?;
+        Ok(backend)
+    }
+
+    async fn first_value(df: DataFrame) -> anyhow::Result<String> {
+        let batches = df.collect().await?;
+        let column = batches[0].column(0);
+        Ok(arrow::util::display::array_value_to_string(column, 0)?)
+    }
+
+    #[tokio::test]
+    async fn variables_should_bind_in_select_list() -> anyhow::Result<()> {
+        let backend = juventus().await?;
+        let df = backend.query("select $pattern").await?;
+        assert_eq!(df.schema().field(0).data_type(), &DataType::Utf8);
+        assert_eq!(first_value(df).await?, "Centre%");
+
+        let df = backend
+            .query("select name, $pattern as p from juventus limit 1")
+            .await?;
+        let batches = df.collect().await?;
+        assert_eq!(batches[0].schema().field(1).name(), "p");
+        let p = arrow::util::display::array_value_to_string(batches[0].column(1), 0)?;
+        assert_eq!(p, "Centre%");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn variables_should_bind_in_like() -> anyhow::Result<()> {
+        let backend = juventus().await?;
+        let sql = "select * from juventus where position like $pattern";
+        assert_eq!(backend.query(sql).await?.count().await?, 7);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn execute_should_bind_params() -> anyhow::Result<()> {
+        let mut backend = juventus().await?;
+        let query = "select $1 as label, count(*) as n from juventus where position like $2";
+        backend
+            .prepare(&PrepareOpts::new("players".into(), query.into()))
+            .await?;
+        let params = vec!["'keepers'".into(), "'Goal%'".into()];
+        let out = backend
+            .execute(&ExecuteOpts::new("players".into(), params))
+            .await?
+            .display()
+            .await?;
+        assert!(out.contains("| keepers | 4 |"), "{}", out);
+
+        let err = backend
+            .execute(&ExecuteOpts::new(
+                "players".into(),
+                vec!["'keepers'".into()],
+            ))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "players expects 2 parameter(s), got 1");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn schema_should_show_the_footer_of_parquet_datasets() -> anyhow::Result<()> {
+        let mut backend = DataFusionBackend::new();
+        let conn = DatabaseConn::Parquet("assets/fixture.parquet".to_string());
+        backend.register("fixture", &conn).await?;
+        let opts = SchemaOpts::new("fixture".into(), false, None);
+        let out = backend.schema(&opts).await?.display().await?;
+        // the schema table, then the footer of the file
+        assert!(out.contains("Decimal128(10, 2)"), "{}", out);
+        assert!(out.contains("rows: 8, row groups: 2"), "{}", out);
+        assert!(out.contains("| BYTE_ARRAY"), "{}", out);
+
+        // a csv dataset has no footer
+        let conn = DatabaseConn::Csv("assets/juventus.csv".to_string());
+        backend.register("juventus", &conn).await?;
+        let opts = SchemaOpts::new("juventus".into(), false, None);
+        let out = backend.schema(&opts).await?.display().await?;
+        assert!(!out.contains("row groups"), "{}", out);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parquet_meta_should_read_datasets_and_paths() -> anyhow::Result<()> {
+        let mut backend = DataFusionBackend::new();
+        let opts = ParquetMetaOpts::new("assets/fixture.parquet".into());
+        let by_path = backend.parquet_meta(&opts).await?.display().await?;
+        assert!(by_path.contains("rows: 8, row groups: 2"), "{}", by_path);
+        assert!(by_path.contains("bloom_filter"), "{}", by_path);
+
+        let conn = DatabaseConn::Parquet("assets/fixture.parquet".to_string());
+        backend.register("fixture", &conn).await?;
+        let opts = ParquetMetaOpts::new("fixture".into());
+        let by_name = backend.parquet_meta(&opts).await?.display().await?;
+        assert!(by_name.contains("rows: 8, row groups: 2"), "{}", by_name);
+
+        let conn = DatabaseConn::Csv("assets/juventus.csv".to_string());
+        backend.register("juventus", &conn).await?;
+        let opts = ParquetMetaOpts::new("juventus".into());
+        let err = backend.parquet_meta(&opts).await.err().unwrap();
+        assert_eq!(err.to_string(), "juventus is not a parquet dataset");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn head_should_slice_project_and_filter() -> anyhow::Result<()> {
+        let backend = juventus().await?;
+        let head = |n, tail, offset, columns: Option<&str>, filter: Option<&str>| {
+            HeadOpts::new(
+                "juventus".into(),
+                n,
+                tail,
+                offset,
+                columns.map(|c| c.split(',').map(String::from).collect()),
+                filter.map(String::from),
+                None,
+                None,
+                None,
+            )
+        };
+        let out = backend
+            .head(&head(Some(2), false, 1, None, None))
+            .await?
+            .display()
+            .await?;
+        assert!(!out.contains("Wojciech Szczesny"), "{}", out);
+        assert!(out.contains("Mattia Perin"), "{}", out);
+        assert!(out.contains("Gianluigi Buffon"), "{}", out);
+
+        let out = backend
+            .head(&head(Some(2), true, 1, None, None))
+            .await?
+            .display()
+            .await?;
+        assert!(out.contains("Paulo Dybala"), "{}", out);
+        assert!(out.contains("Gonzalo Higuaín"), "{}", out);
+        assert!(!out.contains("Mario Mandzukic"), "{}", out);
+
+        let out = backend
+            .head(&head(
+                Some(10),
+                false,
+                0,
+                Some("name,kit number"),
+                Some("position = 'Goalkeeper'"),
+            ))
+            .await?
+            .display()
+            .await?;
+        // a header, 4 keepers and the borders
+        assert_eq!(out.lines().count(), 4 + 4, "{}", out);
+        assert!(!out.contains("position"), "{}", out);
+        assert!(out.contains("kit number"), "{}", out);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn drop_and_rename_should_refuse_datasets_with_dependents() -> anyhow::Result<()> {
+        let mut backend = juventus().await?;
+        let query = "select name from juventus where position = 'Goalkeeper'";
+        backend
+            .derive(&DeriveOpts::new("keepers".into(), query.into()))
+            .await?;
+        backend
+            .alias(&AliasOpts::new("juventus".into(), "juve".into()))
+            .await?;
+
+        let err = backend
+            .drop(&DropOpts::new("juventus".into()))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "can't drop 'juventus', it is used by juve, keepers, drop them first"
+        );
+        let err = backend
+            .rename(&RenameOpts::new("juventus".into(), "juve2".into()))
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().starts_with("can't rename 'juventus'"));
+        // nothing changed
+        assert_eq!(backend.dataset("juventus").await?.count().await?, 27);
+        assert!(backend.dataset("juve2").await.is_err());
+        assert_eq!(backend.dataset("keepers").await?.count().await?, 4);
+
+        // a dataset nothing depends on can go
+        backend
+            .rename(&RenameOpts::new("keepers".into(), "goalkeepers".into()))
+            .await?;
+        backend.drop(&DropOpts::new("goalkeepers".into())).await?;
+        backend.drop(&DropOpts::new("juve".into())).await?;
+        backend
+            .rename(&RenameOpts::new("juventus".into(), "juve".into()))
+            .await?;
+        assert_eq!(backend.dataset("juve").await?.count().await?, 27);
+        backend.drop(&DropOpts::new("juve".into())).await?;
+        assert!(backend.dataset("juve").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn alias_and_refresh_should_follow_the_file() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("taotie-refresh-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("numbers.csv");
+        std::fs::write(&path, "n\n1\n2\n")?;
+
+        let mut backend = DataFusionBackend::new();
+        let conn = DatabaseConn::Csv(path.display().to_string());
+        backend.register("numbers", &conn).await?;
+        backend
+            .alias(&AliasOpts::new("numbers".into(), "nums".into()))
+            .await?;
+        assert_eq!(backend.dataset("nums").await?.count().await?, 2);
+        let err = backend
+            .alias(&AliasOpts::new("numbers".into(), "nums".into()))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "dataset 'nums' already exists");
+
+        std::fs::write(&path, "n\n1\n2\n3\n")?;
+        backend.refresh(&RefreshOpts::new("numbers".into())).await?;
+        assert_eq!(backend.dataset("numbers").await?.count().await?, 3);
+        // the alias is a view, planned again on refresh
+        backend.refresh(&RefreshOpts::new("nums".into())).await?;
+        assert_eq!(backend.dataset("nums").await?.count().await?, 3);
+
+        // a missing file keeps the dataset as it was
+        std::fs::remove_file(&path)?;
+        assert!(backend
+            .refresh(&RefreshOpts::new("numbers".into()))
+            .await
+            .is_err());
+        assert!(backend.dataset("numbers").await.is_ok());
+
+        // tables created by sql have no source to refresh from
+        backend.query("create table t as values (1)").await?;
+        let err = backend
+            .refresh(&RefreshOpts::new("t".into()))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "dataset 't' wasn't registered by taotie, can't refresh it"
+        );
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn derive_should_follow_the_data_and_materialize_should_not() -> anyhow::Result<()> {
+        let mut backend = DataFusionBackend::new();
+        backend.query("create table t as values (1), (2)").await?;
+        let query = "select column1 * 10 as n from t";
+        backend
+            .derive(&DeriveOpts::new("view".into(), query.into()))
+            .await?;
+        backend
+            .materialize(&MaterializeOpts::new("copy".into(), query.into()))
+            .await?;
+        backend
+            .query("insert into t values (3)")
+            .await?
+            .collect()
+            .await?;
+        // the view runs its query every time, the materialized result is a snapshot
+        assert_eq!(backend.dataset("view").await?.count().await?, 3);
+        assert_eq!(backend.dataset("copy").await?.count().await?, 2);
+        // derived datasets can be queried like any other
+        let df = backend.query("select sum(n) from copy").await?;
+        assert_eq!(first_value(df).await?, "30");
+
+        let out = backend.list(&ListOpts::new(true)).await?.display().await?;
+        let line = |name: &str| {
+            out.lines()
+                .find(|l| l.starts_with(&format!("| {} ", name)))
+                .unwrap()
+                .to_string()
+        };
+        assert!(line("view").contains("| view "), "{}", out);
+        assert!(line("view").contains(query), "{}", out);
+        assert!(line("copy").contains("| memory "), "{}", out);
+        assert!(line("copy").contains("| 2 "), "{}", out);
+        assert!(line("copy").contains("B "), "{}", out);
+
+        let err = backend
+            .derive(&DeriveOpts::new("copy".into(), query.into()))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "dataset 'copy' already exists");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn timing_footer_should_count_rows_and_scanned_bytes() -> anyhow::Result<()> {
+        let mut backend = DataFusionBackend::new();
+        let conn = DatabaseConn::Parquet("assets/fixture.parquet".to_string());
+        backend.register("fixture", &conn).await?;
+        let sql = "select name from fixture where id > 6 order by name";
+        let start = backend.timing_start();
+        backend.query(sql).await?.collect().await?;
+        assert_eq!(backend.timing_footer(start), None);
+
+        assert!(backend.timing(Some(true)));
+        let start = backend.timing_start();
+        backend.query(sql).await?.collect().await?;
+        let footer = backend.timing_footer(start).unwrap();
+        assert!(footer.starts_with("2 rows in "), "{}", footer);
+        // bytes read by the parquet scan of the fixture, the first row group is pruned
+        assert!(footer.contains("scanned 352 B"), "{}", footer);
+        // the sort keeps the rows in the memory pool
+        assert!(!footer.ends_with("peak memory 0 B"), "{}", footer);
+
+        // every query of a command is counted, the next command starts from zero
+        let start = backend.timing_start();
+        backend.query("select 1").await?.collect().await?;
+        backend.query("select 2").await?.collect().await?;
+        let footer = backend.timing_footer(start).unwrap();
+        assert!(footer.starts_with("2 queries in "), "{}", footer);
+        assert!(!footer.contains("scanned"), "{}", footer);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn udf_load_should_register_wasm_functions() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("taotie-udf-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("kits.wasm");
+        let wat = r#"
+            (module
+              (func (export "next_kit") (param i64) (result i64)
+                local.get 0 i64.const 1 i64.add)
+              (func (export "lower") (param f64) (result f64)
+                local.get 0))
+        "#;
+        std::fs::write(&path, wat::parse_str(wat)?)?;
+        let path = path.display().to_string();
+
+        let mut backend = juventus().await?;
+        let load = |name: Option<&str>| {
+            let name = name.map(|name| name.to_string());
+            let returns = name.as_ref().map(|_| WasmType::Int64);
+            UdfOpts::new(UdfAction::Load, path.clone(), name, None, vec![], returns)
+        };
+        // `lower` would replace the built-in function, nothing is registered
+        let err = backend.udf(&load(None)).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "function lower of plugin kits.wasm would replace the built-in function lower"
+        );
+        assert_eq!(count(&backend, "select next_kit(1)").await.ok(), None);
+
+        let mut opts = load(Some("kit_after"));
+        opts.export = Some("next_kit".into());
+        opts.args = vec![WasmType::Int64];
+        let msg = backend.udf(&opts).await?;
+        assert_eq!(msg, "loaded 1 function(s) from kits.wasm: kit_after(Int64)");
+        let df = backend
+            .query(r#"select max(kit_after("kit number")) from juventus"#)
+            .await?;
+        assert_eq!(first_value(df).await?, "78");
+        let functions = backend.functions(&FunctionsOpts).await?.display().await?;
+        assert!(functions.contains("next_kit exported by kits.wasm"));
+
+        let err = backend.udf(&opts).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "function kit_after of plugin kits.wasm is already registered by plugin kits.wasm"
+        );
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    async fn count(backend: &DataFusionBackend, sql: &str) -> anyhow::Result<usize> {
+        Ok(backend.query(sql).await?.count().await?)
+    }
+
+    #[tokio::test]
+    async fn variables_should_bind_typed_values_not_text() -> anyhow::Result<()> {
+        let mut backend = juventus().await?;
+        let msg = backend
+            .define(&LetOpts::new(Some("min_kit".into()), vec!["20".into()]))
+            .await?;
+        assert_eq!(msg, "$min_kit = 20 (Int64)");
+        assert_eq!(
+            count(
+                &backend,
+                r#"select * from juventus where "kit number" > $min_kit"#
+            )
+            .await?,
+            count(
+                &backend,
+                r#"select * from juventus where "kit number" > 20"#
+            )
+            .await?
+        );
+
+        // quotes in the value stay in the value
+        let evil = r#"'x'' or ''1''=''1'"#;
+        let msg = backend
+            .define(&LetOpts::new(Some("evil".into()), vec![evil.into()]))
+            .await?;
+        assert_eq!(msg, "$evil = x' or '1'='1 (Utf8)");
+        assert_eq!(
+            count(&backend, "select * from juventus where name = $evil").await?,
+            0
+        );
+
+        let err = backend.query("select $missing").await.err().unwrap();
+        assert!(
+            err.to_string()
+                .contains("variable $missing is not defined, set it with `.let missing = <value>`"),
+            "{}",
+            err
+        );
+        let err = backend.query("select $1").await.err().unwrap();
+        assert!(
+            err.to_string()
+                .contains("$1 is a parameter of prepared statements"),
+            "{}",
+            err
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn execute_should_mix_params_and_variables() -> anyhow::Result<()> {
+        let mut backend = juventus().await?;
+        let query = r#"select count(*) as n from juventus where "kit number" < $1 and position like $pattern"#;
+        let msg = backend
+            .prepare(&PrepareOpts::new("centres".into(), query.into()))
+            .await?;
+        assert_eq!(msg, "prepared centres with parameters $1, $pattern");
+        let run = |param: &str| ExecuteOpts::new("centres".into(), vec![param.into()]);
+        let all = backend.execute(&run("100")).await?.display().await?;
+        let few = backend.execute(&run("10")).await?.display().await?;
+        assert!(all.contains("| 7 |"), "{}", all);
+        assert!(!few.contains("| 7 |"), "{}", few);
+
+        let err = backend
+            .execute(&ExecuteOpts::new("missing".into(), vec![]))
+            .await
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .starts_with("prepared statement 'missing' not found"));
+        Ok(())
     }
 }
@@ -0,0 +1,193 @@
+//! 偏度(skew)和峰度(kurtosis)的聚合函数
+//!
+//! 逐行更新均值和二到四阶中心矩, 分区之间按Pébay的公式合并; 不用原点矩 E[x^k] 相减,
+//! 否则像epoch秒这种偏移很大的数据会因为相减抵消丢失全部精度
+
+use std::sync::Arc;
+
+use arrow::{array::ArrayRef, datatypes::DataType};
+use datafusion::{
+    common::cast::as_float64_array,
+    error::Result,
+    logical_expr::{create_udaf, Accumulator, AggregateUDF, Volatility},
+    prelude::Expr,
+    scalar::ScalarValue,
+};
+
+/// population skewness of `x`, a Float64 expression
+pub fn skew(x: Expr) -> Expr {
+    moments_udaf("skew", Moment::Skew).call(vec![x])
+}
+
+/// excess kurtosis of `x`, a Float64 expression, 0 for a normal distribution
+pub fn kurtosis(x: Expr) -> Expr {
+    moments_udaf("kurtosis", Moment::Kurtosis).call(vec![x])
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Moment {
+    Skew,
+    Kurtosis,
+}
+
+fn moments_udaf(name: &str, moment: Moment) -> AggregateUDF {
+    create_udaf(
+        name,
+        vec![DataType::Float64],
+        Arc::new(DataType::Float64),
+        Volatility::Immutable,
+        Arc::new(move |_| {
+            Ok(Box::new(MomentsAccumulator {
+                moments: Moments::default(),
+                moment,
+            }))
+        }),
+        // n, mean and the sums of the 2nd to 4th powers of the deviations
+        Arc::new(vec![DataType::Float64; 5]),
+    )
+}
+
+/// count, mean and the sums of the 2nd, 3rd and 4th powers of the deviations from it
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Moments {
+    n: f64,
+    mean: f64,
+    m2: f64,
+    m3: f64,
+    m4: f64,
+}
+
+impl Moments {
+    fn push(&mut self, x: f64) {
+        self.merge(&Moments {
+            n: 1.0,
+            mean: x,
+            ..Default::default()
+        });
+    }
+
+    /// combine the moments of two disjoint sets of values
+    fn merge(&mut self, b: &Moments) {
+        if b.n == 0.0 {
+            return;
+        }
+        if self.n == 0.0 {
+            *self = *b;
+            return;
+        }
+        let a = *self;
+        let n = a.n + b.n;
+        let d = b.mean - a.mean;
+        let (d2, d3, d4) = (d * d, d * d * d, d * d * d * d);
+        self.n = n;
+        self.mean = a.mean + d * b.n / n;
+        self.m2 = a.m2 + b.m2 + d2 * a.n * b.n / n;
+        self.m3 = a.m3
+            + b.m3
+            + d3 * a.n * b.n * (a.n - b.n) / (n * n)
+            + 3.0 * d * (a.n * b.m2 - b.n * a.m2) / n;
+        self.m4 = a.m4
+            + b.m4
+            + d4 * a.n * b.n * (a.n * a.n - a.n * b.n + b.n * b.n) / (n * n * n)
+            + 6.0 * d2 * (a.n * a.n * b.m2 + b.n * b.n * a.m2) / (n * n)
+            + 4.0 * d * (a.n * b.m3 - b.n * a.m3) / n;
+    }
+
+    fn skew(&self) -> Option<f64> {
+        (self.m2 > 0.0).then(|| self.n.sqrt() * self.m3 / self.m2.powf(1.5))
+    }
+
+    fn kurtosis(&self) -> Option<f64> {
+        (self.m2 > 0.0).then(|| self.n * self.m4 / (self.m2 * self.m2) - 3.0)
+    }
+}
+
+#[derive(Debug)]
+struct MomentsAccumulator {
+    moments: Moments,
+    moment: Moment,
+}
+
+impl Accumulator for MomentsAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        for x in as_float64_array(&values[0])?.iter().flatten() {
+            self.moments.push(x);
+        }
+        Ok(())
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        let value = match self.moment {
+            Moment::Skew => self.moments.skew(),
+            Moment::Kurtosis => self.moments.kurtosis(),
+        };
+        Ok(ScalarValue::Float64(value))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        let Moments {
+            n,
+            mean,
+            m2,
+            m3,
+            m4,
+        } = self.moments;
+        Ok([n, mean, m2, m3, m4]
+            .into_iter()
+            .map(|v| ScalarValue::Float64(Some(v)))
+            .collect())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        let states = states
+            .iter()
+            .map(|state| as_float64_array(state))
+            .collect::<Result<Vec<_>>>()?;
+        for r in 0..states[0].len() {
+            self.moments.merge(&Moments {
+                n: states[0].value(r),
+                mean: states[1].value(r),
+                m2: states[2].value(r),
+                m3: states[3].value(r),
+                m4: states[4].value(r),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moments(values: &[f64]) -> Moments {
+        let mut moments = Moments::default();
+        values.iter().for_each(|x| moments.push(*x));
+        moments
+    }
+
+    #[test]
+    fn merge_should_match_a_single_pass() {
+        let values = [1.0, 2.0, 2.0, 3.0, 7.0, 11.0, 11.5];
+        let mut merged = moments(&values[..3]);
+        merged.merge(&moments(&values[3..]));
+        let single = moments(&values);
+        for (a, b) in [
+            (merged.skew(), single.skew()),
+            (merged.kurtosis(), single.kurtosis()),
+        ] {
+            assert!((a.unwrap() - b.unwrap()).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn moments_should_be_none_for_constant_values() {
+        let constant = moments(&[5.0; 4]);
+        assert_eq!(constant.skew(), None);
+        assert_eq!(constant.kurtosis(), None);
+    }
+}
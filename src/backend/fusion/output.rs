@@ -0,0 +1,96 @@
+//! sql和head的结果按 `set format` / `set max_rows` 输出: 表格, csv或者ndjson,
+//! 超出max_rows的行不显示, 在最后说明一共有多少行
+
+use anyhow::Result;
+use arrow::{
+    array::RecordBatch, csv::WriterBuilder, json::LineDelimitedWriter,
+    util::pretty::pretty_format_batches,
+};
+use datafusion::prelude::DataFrame;
+
+use crate::cli::set::OutputFormat;
+
+/// taotie's own display settings, the datafusion ones live in the session config
+#[derive(Debug, Clone, Default)]
+pub struct DisplaySettings {
+    pub format: OutputFormat,
+    /// unlimited when `None`
+    pub max_rows: Option<usize>,
+}
+
+/// rows of a query, rendered with the display settings when shown
+#[derive(Debug)]
+pub struct Rows {
+    pub df: DataFrame,
+    pub settings: DisplaySettings,
+}
+
+impl Rows {
+    pub fn new(df: DataFrame, settings: &DisplaySettings) -> Self {
+        Self {
+            df,
+            settings: settings.clone(),
+        }
+    }
+
+    pub async fn render(&self) -> Result<String> {
+        let batches = self.df.clone().collect().await?;
+        // DDL such as `CREATE VIEW` returns nothing
+        if self.df.schema().fields().is_empty() {
+            return Ok("OK".to_string());
+        }
+        let total: usize = batches.iter().map(|b| b.num_rows()).sum();
+        let (mut batches, shown) = match self.settings.max_rows {
+            Some(max) if total > max => (truncate(batches, max), max),
+            _ => (batches, total),
+        };
+        let mut out = match self.settings.format {
+            OutputFormat::Table => {
+                // keep the header when nothing matches
+                if batches.is_empty() {
+                    batches.push(RecordBatch::new_empty(self.df.schema().inner().clone()));
+                }
+                pretty_format_batches(&batches)?.to_string()
+            }
+            OutputFormat::Csv => {
+                let mut writer = WriterBuilder::new().with_header(true).build(vec![]);
+                for batch in batches.iter() {
+                    writer.write(batch)?;
+                }
+                String::from_utf8(writer.into_inner())?
+            }
+            OutputFormat::Json => {
+                let mut writer = LineDelimitedWriter::new(vec![]);
+                writer.write_batches(&batches.iter().collect::<Vec<_>>())?;
+                writer.finish()?;
+                String::from_utf8(writer.into_inner())?
+            }
+        };
+        if shown < total {
+            let out = out.trim_end().to_string();
+            return Ok(format!(
+                "{}\n({} of {} rows shown, see `.set max_rows`)",
+                out, shown, total
+            ));
+        }
+        if out.ends_with('\n') {
+            out.pop();
+        }
+        Ok(out)
+    }
+}
+
+/// the first `max` rows
+fn truncate(batches: Vec<RecordBatch>, max: usize) -> Vec<RecordBatch> {
+    let mut left = max;
+    let mut kept = vec![];
+    for batch in batches {
+        if left == 0 {
+            break;
+        }
+        let n = batch.num_rows().min(left);
+        kept.push(batch.slice(0, n));
+        left -= n;
+    }
+    kept
+}
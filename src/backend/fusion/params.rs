@@ -0,0 +1,206 @@
+//! 查询参数: `$name` 是 `let` 定义的会话变量, `$1`, `$2` ... 是 `execute` 传给预编译语句的参数
+//!
+//! 绑定的是有类型的值而不是拼接出来的sql文本: 规划前把占位符重新编号成 `$1`, `$2` ...,
+//! 用 `PREPARE` 带上值的类型, 这样 `select $x`, `like $x` 也能规划; 之后值替换占位符,
+//! 没有sql类型的值(比如struct)由占位符推断类型, 比如和它比较的列的类型
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ops::ControlFlow,
+    sync::Arc,
+};
+
+use datafusion::{
+    arrow::datatypes::{DataType, TimeUnit},
+    common::{
+        plan_datafusion_err, plan_err,
+        tree_node::{Transformed, TreeNode},
+    },
+    error::Result,
+    logical_expr::{expr::Placeholder, Expr, LogicalPlan},
+    scalar::ScalarValue,
+    sql::{
+        parser::Statement,
+        sqlparser::ast::{
+            visit_expressions, visit_expressions_mut, ArrayElemTypeDef, DataType as SQLDataType,
+            ExactNumberInfo, Expr as SQLExpr, Ident, Statement as SQLStatement, TimezoneInfo,
+            Value,
+        },
+    },
+};
+
+/// values bound to the placeholders of a query
+#[derive(Debug, Clone, Copy)]
+pub struct Params<'a> {
+    pub variables: &'a BTreeMap<String, ScalarValue>,
+    /// `None` outside `execute`
+    pub positional: Option<&'a [ScalarValue]>,
+}
+
+impl Params<'_> {
+    /// the statement with its placeholders renumbered `$1`, `$2` ... and wrapped in a
+    /// `PREPARE` with the types of their values, and the values of the new numbers
+    ///
+    /// values without a sql type come last and stay untyped, `bind` casts them to the type
+    /// inferred from the plan; statements other than sql ones are left as they are
+    pub fn prepare(&self, statement: Statement) -> Result<(Statement, Vec<ScalarValue>)> {
+        let ids = placeholders(&statement);
+        let Statement::Statement(inner) = statement else {
+            return Ok((statement, vec![]));
+        };
+        if ids.is_empty() {
+            return Ok((Statement::Statement(inner), vec![]));
+        }
+        let mut params = ids
+            .iter()
+            .map(|id| Ok((id.as_str(), self.value(id, None)?)))
+            .collect::<Result<Vec<_>>>()?;
+        params.sort_by_key(|(_, value)| sql_type(&value.data_type()).is_none());
+        let numbers: HashMap<&str, String> = params
+            .iter()
+            .enumerate()
+            .map(|(i, (id, _))| (*id, format!("${}", i + 1)))
+            .collect();
+        let mut inner = inner;
+        let _ = visit_expressions_mut(&mut *inner, |e| {
+            if let SQLExpr::Value(Value::Placeholder(id)) = e {
+                if let Some(number) = numbers.get(id.as_str()) {
+                    *id = number.clone();
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+        let data_types = params
+            .iter()
+            .map_while(|(_, value)| sql_type(&value.data_type()))
+            .collect();
+        let values = params.into_iter().map(|(_, value)| value).collect();
+        let statement = SQLStatement::Prepare {
+            name: Ident::new("taotie"),
+            data_types,
+            statement: inner,
+        };
+        Ok((Statement::Statement(Box::new(statement)), values))
+    }
+
+    /// replace every placeholder of the plan with its value
+    pub fn bind(&self, plan: LogicalPlan) -> Result<LogicalPlan> {
+        plan.transform_up_with_subqueries(|plan| {
+            let schema = Arc::clone(plan.schema());
+            plan.map_expressions(|e| self.bind_expr(e.infer_placeholder_types(&schema)?))?
+                .map_data(|plan| plan.recompute_schema())
+        })
+        .map(|t| t.data)
+    }
+
+    /// replace every placeholder of the expression with its value
+    pub fn bind_expr(&self, expr: Expr) -> Result<Transformed<Expr>> {
+        expr.transform_up(|e| match e {
+            Expr::Placeholder(Placeholder { id, data_type }) => {
+                let value = self.value(&id, data_type.as_ref())?;
+                Ok(Transformed::yes(Expr::Literal(value)))
+            }
+            e => Ok(Transformed::no(e)),
+        })
+    }
+
+    fn value(&self, id: &str, data_type: Option<&DataType>) -> Result<ScalarValue> {
+        let name = id.trim_start_matches('$');
+        let value = match (name.parse::<usize>(), self.positional) {
+            (Ok(i), Some(values)) => i
+                .checked_sub(1)
+                .and_then(|i| values.get(i))
+                .ok_or_else(|| plan_datafusion_err!("no value for {}", id))?,
+            (Ok(_), None) => {
+                return plan_err!(
+                    "{} is a parameter of prepared statements, use `.prepare` and `.execute`",
+                    id
+                )
+            }
+            (Err(_), _) => self.variables.get(name).ok_or_else(|| {
+                plan_datafusion_err!(
+                    "variable {} is not defined, set it with `.let {} = <value>`",
+                    id,
+                    name
+                )
+            })?,
+        };
+        match data_type {
+            Some(data_type) if value.data_type() != *data_type => {
+                value.cast_to(data_type).map_err(|_| {
+                    plan_datafusion_err!("can't bind {} to {} as {}", value, id, data_type)
+                })
+            }
+            _ => Ok(value.clone()),
+        }
+    }
+}
+
+/// ids of the placeholders of a statement, e.g. `$1` or `$min_age`
+pub fn placeholders(statement: &Statement) -> BTreeSet<String> {
+    let mut ids = BTreeSet::new();
+    if let Statement::Statement(statement) = statement {
+        let _ = visit_expressions(statement, |e| {
+            if let SQLExpr::Value(Value::Placeholder(id)) = e {
+                ids.insert(id.clone());
+            }
+            ControlFlow::<()>::Continue(())
+        });
+    }
+    ids
+}
+
+/// number of positional parameters, the highest `$n`
+pub fn positional_count(ids: &BTreeSet<String>) -> usize {
+    ids.iter()
+        .filter_map(|id| id.trim_start_matches('$').parse::<usize>().ok())
+        .max()
+        .unwrap_or_default()
+}
+
+/// the sql type the planner turns back into the type of the value, `None` for e.g. structs
+fn sql_type(data_type: &DataType) -> Option<SQLDataType> {
+    let sql_type = match data_type {
+        DataType::Boolean => SQLDataType::Boolean,
+        DataType::Int8 => SQLDataType::TinyInt(None),
+        DataType::Int16 => SQLDataType::SmallInt(None),
+        DataType::Int32 => SQLDataType::Int(None),
+        DataType::Int64 => SQLDataType::BigInt(None),
+        DataType::UInt8 => SQLDataType::UnsignedTinyInt(None),
+        DataType::UInt16 => SQLDataType::UnsignedSmallInt(None),
+        DataType::UInt32 => SQLDataType::UnsignedInt(None),
+        DataType::UInt64 => SQLDataType::UnsignedBigInt(None),
+        DataType::Float32 => SQLDataType::Real,
+        DataType::Float64 => SQLDataType::Double,
+        DataType::Utf8 | DataType::LargeUtf8 => SQLDataType::Text,
+        DataType::Date32 | DataType::Date64 => SQLDataType::Date,
+        DataType::Timestamp(_, None) => SQLDataType::Timestamp(None, TimezoneInfo::None),
+        DataType::Timestamp(_, Some(_)) => SQLDataType::Timestamp(None, TimezoneInfo::Tz),
+        DataType::Time64(TimeUnit::Nanosecond) => SQLDataType::Time(None, TimezoneInfo::None),
+        DataType::Decimal128(precision, scale) if *scale >= 0 => SQLDataType::Decimal(
+            ExactNumberInfo::PrecisionAndScale(*precision as u64, *scale as u64),
+        ),
+        DataType::Binary => SQLDataType::Bytea,
+        DataType::Interval(_) => SQLDataType::Interval,
+        DataType::List(field) => SQLDataType::Array(ArrayElemTypeDef::AngleBracket(Box::new(
+            sql_type(field.data_type())?,
+        ))),
+        _ => return None,
+    };
+    Some(sql_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positional_count_should_be_the_highest_number() {
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<BTreeSet<_>>();
+        assert_eq!(positional_count(&ids(&[])), 0);
+        assert_eq!(positional_count(&ids(&["$name"])), 0);
+        assert_eq!(positional_count(&ids(&["$1", "$2", "$name"])), 2);
+        // lexical order of the ids doesn't matter, a gap still counts
+        assert_eq!(positional_count(&ids(&["$10", "$2"])), 10);
+    }
+}
@@ -0,0 +1,120 @@
+//! udf插件: 一个插件提供一组scalar/aggregate函数, 创建session时注册进去
+//!
+//! 插件是编译进来的, 自己的插件实现 `UdfPlugin`, 加到 `main.rs` 的 `plugins()` 里,
+//! 由 `ReplContext::try_new_with_plugins` 传入;
+//! taotie自带的一组在 `functions` 里, 总是会注册; `udf load` 加载的wasm模块也是一个插件, 见 `wasm`
+
+use std::sync::Arc;
+
+use arrow::{
+    array::{RecordBatch, StringArray},
+    datatypes::{DataType, Field, Schema},
+};
+use datafusion::logical_expr::{AggregateUDF, ScalarUDF};
+
+/// a function of a plugin
+#[derive(Debug)]
+pub enum Udf {
+    Scalar(ScalarUDF),
+    Aggregate(AggregateUDF),
+}
+
+#[derive(Debug)]
+pub struct PluginFunction {
+    pub udf: Udf,
+    /// e.g. `email_domain(email)`
+    pub usage: String,
+    pub description: String,
+}
+
+/// a set of functions registered into the session
+pub trait UdfPlugin: Send {
+    fn name(&self) -> &str;
+    fn functions(&self) -> Vec<PluginFunction>;
+}
+
+/// a registered function, listed by `functions`
+#[derive(Debug)]
+pub struct FunctionInfo {
+    pub name: String,
+    pub kind: &'static str,
+    pub plugin: String,
+    pub usage: String,
+    pub description: String,
+}
+
+impl Udf {
+    pub fn name(&self) -> &str {
+        match self {
+            Udf::Scalar(udf) => udf.name(),
+            Udf::Aggregate(udf) => udf.name(),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Udf::Scalar(_) => "scalar",
+            Udf::Aggregate(_) => "aggregate",
+        }
+    }
+}
+
+impl PluginFunction {
+    pub fn scalar(
+        udf: ScalarUDF,
+        usage: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        Self {
+            udf: Udf::Scalar(udf),
+            usage: usage.into(),
+            description: description.into(),
+        }
+    }
+
+    pub fn aggregate(
+        udf: AggregateUDF,
+        usage: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        Self {
+            udf: Udf::Aggregate(udf),
+            usage: usage.into(),
+            description: description.into(),
+        }
+    }
+}
+
+/// one row per function, sorted by plugin and name
+pub fn functions_batch(functions: &[FunctionInfo]) -> anyhow::Result<RecordBatch> {
+    let mut functions = functions.iter().collect::<Vec<_>>();
+    functions.sort_by(|a, b| (&a.plugin, &a.name).cmp(&(&b.plugin, &b.name)));
+    let schema = Schema::new(vec![
+        Field::new("name", DataType::Utf8, false),
+        Field::new("kind", DataType::Utf8, false),
+        Field::new("plugin", DataType::Utf8, false),
+        Field::new("usage", DataType::Utf8, false),
+        Field::new("description", DataType::Utf8, false),
+    ]);
+    let batch = RecordBatch::try_new(
+        Arc::new(schema),
+        vec![
+            Arc::new(StringArray::from_iter_values(
+                functions.iter().map(|f| f.name.as_str()),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                functions.iter().map(|f| f.kind),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                functions.iter().map(|f| f.plugin.as_str()),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                functions.iter().map(|f| f.usage.as_str()),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                functions.iter().map(|f| f.description.as_str()),
+            )),
+        ],
+    )?;
+    Ok(batch)
+}
@@ -0,0 +1,558 @@
+//! 数据画像(profile), 在describe的基础上生成每一列的:
+//! 类型, 空值率, 去重率, 直方图, 高频值, IQR异常值, 字符串长度与模式分布,
+//! 以及数值列两两之间的相关系数, 输出为json或者自包含的html报告
+
+use std::fmt::Write;
+
+use anyhow::Result;
+use arrow::{
+    array::{ArrayRef, RecordBatch, StringArray, UInt64Array},
+    datatypes::{DataType, Field, Schema},
+};
+use datafusion::{
+    functions_aggregate::{approx_distinct::approx_distinct, correlation::corr, sum::sum},
+    prelude::{cast, ident, lit, regexp_replace, when, DataFrame, Expr},
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+use super::describe2::{
+    float_value, frequent_values, is_categorical, is_list, render_values, transform,
+    DataFrameDescriber, DescribeMethod,
+};
+use super::flatten::is_nested;
+use super::hist::{histogram, Bin};
+
+pub struct DataFrameProfiler {
+    name: String,
+    df: DataFrame,
+    bins: usize,
+    top_k: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Profile {
+    pub dataset: String,
+    pub rows: u64,
+    pub columns: Vec<ColumnProfile>,
+    pub correlations: Vec<Correlation>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnKind {
+    Numeric,
+    Temporal,
+    String,
+    List,
+    Other,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ColumnProfile {
+    pub name: String,
+    pub data_type: String,
+    pub kind: ColumnKind,
+    pub count: u64,
+    pub null_count: u64,
+    pub null_ratio: f64,
+    /// approximate, not available for list columns
+    pub distinct_count: Option<u64>,
+    pub distinct_ratio: Option<f64>,
+    /// statistics of the value, or of the length for string and list columns
+    pub stats: Vec<Stat>,
+    pub histogram: Vec<Bin>,
+    /// values outside [p25 - 1.5 * IQR, p75 + 1.5 * IQR]
+    pub outliers: Option<u64>,
+    pub top_values: Vec<Frequency>,
+    /// string shapes: upper case letters as `A`, lower case as `a`, digits as `9`
+    pub patterns: Vec<Frequency>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Stat {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Frequency {
+    pub value: String,
+    pub count: u64,
+    pub ratio: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Correlation {
+    pub left: String,
+    pub right: String,
+    pub value: Option<f64>,
+}
+
+impl ColumnKind {
+    fn from_data_type(dt: &DataType) -> Self {
+        match dt {
+            dt if dt.is_temporal() => ColumnKind::Temporal,
+            dt if dt.is_numeric() => ColumnKind::Numeric,
+            dt if is_categorical(dt) => ColumnKind::String,
+            DataType::List(_) => ColumnKind::List,
+            _ => ColumnKind::Other,
+        }
+    }
+
+    /// stats and histogram are computed on the length of string and list values
+    fn by_length(&self) -> bool {
+        matches!(self, ColumnKind::String | ColumnKind::List)
+    }
+}
+
+/// statistics shown for every column
+const PROFILE_METHODS: [DescribeMethod; 9] = [
+    DescribeMethod::Count,
+    DescribeMethod::NullCount,
+    DescribeMethod::Mean,
+    DescribeMethod::Std,
+    DescribeMethod::Min,
+    DescribeMethod::Max,
+    DescribeMethod::Percentile(25),
+    DescribeMethod::Percentile(50),
+    DescribeMethod::Percentile(75),
+];
+
+impl DataFrameProfiler {
+    pub fn new(name: impl Into<String>, df: DataFrame, bins: usize, top_k: usize) -> Self {
+        Self {
+            name: name.into(),
+            df,
+            bins: bins.max(1),
+            top_k,
+        }
+    }
+
+    pub async fn profile(&self) -> Result<Profile> {
+        let describer =
+            DataFrameDescriber::try_new(self.df.clone())?.with_methods(PROFILE_METHODS.to_vec());
+        let stats = describer.describe().await?;
+        let distincts = self.distinct_counts().await?;
+
+        let fields = self.df.schema().fields().clone();
+        // columns which can't be described, e.g. structs, have no statistics
+        let mut values = vec![];
+        for field in fields.iter() {
+            let column = match describer.stats_column(&stats, field.name()) {
+                Some(array) => Some(
+                    (0..PROFILE_METHODS.len())
+                        .map(|j| float_value(array, j))
+                        .collect::<Result<Vec<_>>>()?,
+                ),
+                None => None,
+            };
+            values.push(column);
+        }
+        let outliers = self.outliers(&values).await?;
+
+        let mut columns = vec![];
+        let mut rows = 0;
+        for (i, field) in fields.iter().enumerate() {
+            let Some(column) = &values[i] else {
+                continue;
+            };
+            let kind = ColumnKind::from_data_type(field.data_type());
+            let stat = |method: DescribeMethod| {
+                let j = PROFILE_METHODS.iter().position(|m| *m == method).unwrap();
+                column[j]
+            };
+            let count = stat(DescribeMethod::Count).unwrap_or_default() as u64;
+            let null_count = stat(DescribeMethod::NullCount).unwrap_or_default() as u64;
+            rows = count + null_count;
+            let distinct_count = distincts[i].map(|d| d.min(count));
+
+            let stat_names = PROFILE_METHODS.iter().map(|m| match kind.by_length() {
+                true => format!("length_{}", m),
+                false => m.to_string(),
+            });
+            let rendered = render_values(&PROFILE_METHODS, column.clone(), field.data_type())?;
+            let stats = stat_names
+                .zip(rendered)
+                .map(|(name, value)| Stat { name, value })
+                .collect();
+
+            let histogram = match (stat(DescribeMethod::Min), stat(DescribeMethod::Max)) {
+                (Some(min), Some(max)) if kind != ColumnKind::Other => {
+                    histogram(&self.df, field, min, max, self.bins).await?
+                }
+                _ => vec![],
+            };
+            let (top_values, patterns) = match kind {
+                ColumnKind::List | ColumnKind::Other => (vec![], vec![]),
+                ColumnKind::String => {
+                    let text = cast(ident(field.name()), DataType::Utf8);
+                    (
+                        self.frequencies(text.clone(), count).await?,
+                        self.frequencies(pattern(text), count).await?,
+                    )
+                }
+                _ => {
+                    let text = cast(ident(field.name()), DataType::Utf8);
+                    (self.frequencies(text, count).await?, vec![])
+                }
+            };
+
+            columns.push(ColumnProfile {
+                name: field.name().clone(),
+                data_type: field.data_type().to_string(),
+                kind,
+                count,
+                null_count,
+                null_ratio: ratio(null_count, count + null_count),
+                distinct_count,
+                distinct_ratio: distinct_count.map(|d| ratio(d, count)),
+                stats,
+                histogram,
+                outliers: outliers[i],
+                top_values,
+                patterns,
+            });
+        }
+
+        Ok(Profile {
+            dataset: self.name.clone(),
+            rows,
+            columns,
+            correlations: self.correlations().await?,
+        })
+    }
+
+    /// approx distinct count of every column in a single aggregate, lists are skipped
+    async fn distinct_counts(&self) -> Result<Vec<Option<u64>>> {
+        let fields = self.df.schema().fields().clone();
+        let expr: Vec<Expr> = fields
+            .iter()
+            .filter(|f| !is_list(f.data_type()) && !is_nested(f.data_type()))
+            .map(|f| {
+                // approx_distinct only supports integers and strings
+                let e = match f.data_type().is_integer() {
+                    true => ident(f.name()),
+                    false => cast(ident(f.name()), DataType::Utf8),
+                };
+                approx_distinct(e).alias(f.name())
+            })
+            .collect();
+        if expr.is_empty() {
+            return Ok(vec![None; fields.len()]);
+        }
+        let batches = self.df.clone().aggregate(vec![], expr)?.collect().await?;
+        let row = &batches[0];
+        fields
+            .iter()
+            .map(|f| match row.column_by_name(f.name()) {
+                Some(array) => Ok(float_value(array, 0)?.map(|v| v as u64)),
+                None => Ok(None),
+            })
+            .collect()
+    }
+
+    /// number of values outside the IQR fences of every numeric and temporal column,
+    /// computed in a single aggregate
+    async fn outliers(&self, values: &[Option<Vec<Option<f64>>>]) -> Result<Vec<Option<u64>>> {
+        let p = |i: usize, p: u8| {
+            let j = PROFILE_METHODS
+                .iter()
+                .position(|m| *m == DescribeMethod::Percentile(p))
+                .unwrap();
+            values[i].as_ref().and_then(|column| column[j])
+        };
+        let fields = self.df.schema().fields().clone();
+        let mut expr = vec![];
+        for (i, field) in fields.iter().enumerate() {
+            let kind = ColumnKind::from_data_type(field.data_type());
+            if !matches!(kind, ColumnKind::Numeric | ColumnKind::Temporal) {
+                continue;
+            }
+            let (Some(p25), Some(p75)) = (p(i, 25), p(i, 75)) else {
+                continue;
+            };
+            let iqr = p75 - p25;
+            let x = cast(transform(field), DataType::Float64);
+            let outside = x
+                .clone()
+                .lt(lit(p25 - 1.5 * iqr))
+                .or(x.gt(lit(p75 + 1.5 * iqr)));
+            let flag = when(outside, lit(1_i64)).otherwise(lit(0_i64))?;
+            expr.push(sum(flag).alias(field.name()));
+        }
+        if expr.is_empty() {
+            return Ok(vec![None; fields.len()]);
+        }
+        let batches = self.df.clone().aggregate(vec![], expr)?.collect().await?;
+        let row = &batches[0];
+        fields
+            .iter()
+            .map(|f| match row.column_by_name(f.name()) {
+                Some(array) => Ok(Some(float_value(array, 0)?.unwrap_or_default() as u64)),
+                None => Ok(None),
+            })
+            .collect()
+    }
+
+    async fn frequencies(&self, expr: Expr, non_null: u64) -> Result<Vec<Frequency>> {
+        if self.top_k == 0 {
+            return Ok(vec![]);
+        }
+        let values = frequent_values(&self.df, expr, self.top_k).await?;
+        let values = values
+            .into_iter()
+            .map(|(value, count)| Frequency {
+                value,
+                count: count as u64,
+                ratio: ratio(count as u64, non_null),
+            })
+            .collect();
+        Ok(values)
+    }
+
+    /// pearson correlation of every pair of numeric columns in a single aggregate
+    async fn correlations(&self) -> Result<Vec<Correlation>> {
+        let names: Vec<String> = self
+            .df
+            .schema()
+            .fields()
+            .iter()
+            .filter(|f| f.data_type().is_numeric())
+            .map(|f| f.name().clone())
+            .collect();
+        let mut pairs = vec![];
+        let mut expr = vec![];
+        for (i, left) in names.iter().enumerate() {
+            for right in names.iter().skip(i + 1) {
+                let x = cast(ident(left), DataType::Float64);
+                let y = cast(ident(right), DataType::Float64);
+                expr.push(corr(x, y).alias(format!("__corr_{}", pairs.len())));
+                pairs.push((left.clone(), right.clone()));
+            }
+        }
+        if expr.is_empty() {
+            return Ok(vec![]);
+        }
+        let batches = self.df.clone().aggregate(vec![], expr)?.collect().await?;
+        let row = &batches[0];
+        pairs
+            .into_iter()
+            .enumerate()
+            .map(|(i, (left, right))| {
+                let value = float_value(row.column(i), 0)?.filter(|v| v.is_finite());
+                Ok(Correlation { left, right, value })
+            })
+            .collect()
+    }
+}
+
+impl Profile {
+    /// one row per column, shown in the repl after the report is written
+    pub fn summary(&self) -> Result<RecordBatch> {
+        let percent = |v: Option<f64>| v.map(|v| format_ratio(Some(v)));
+        let schema = Schema::new(vec![
+            Field::new("column", DataType::Utf8, false),
+            Field::new("kind", DataType::Utf8, false),
+            Field::new("null_ratio", DataType::Utf8, true),
+            Field::new("distinct_ratio", DataType::Utf8, true),
+            Field::new("outliers", DataType::UInt64, true),
+        ]);
+        let c = &self.columns;
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from_iter_values(c.iter().map(|c| &c.name))),
+            Arc::new(StringArray::from_iter_values(
+                c.iter().map(|c| kind_name(c.kind)),
+            )),
+            Arc::new(StringArray::from_iter(
+                c.iter().map(|c| percent(Some(c.null_ratio))),
+            )),
+            Arc::new(StringArray::from_iter(
+                c.iter().map(|c| percent(c.distinct_ratio)),
+            )),
+            Arc::new(UInt64Array::from_iter(c.iter().map(|c| c.outliers))),
+        ];
+        Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// a self-contained static html page, no external scripts or styles
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        let title = format!("Taotie profile: {}", escape(&self.dataset));
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+             <style>{STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n\
+             <p>{} rows, {} columns</p>\n",
+            self.rows,
+            self.columns.len()
+        );
+
+        html.push_str("<h2>Overview</h2>\n<table>\n<tr><th>column</th><th>type</th><th>kind</th><th>null</th><th>distinct</th><th>outliers</th></tr>\n");
+        for (i, c) in self.columns.iter().enumerate() {
+            let _ = writeln!(
+                html,
+                "<tr><td><a href=\"#col-{i}\">{name}</a></td><td>{dt}</td><td>{kind}</td><td>{null}</td><td>{distinct}</td><td>{outliers}</td></tr>",
+                name = escape(&c.name),
+                dt = escape(&c.data_type),
+                kind = kind_name(c.kind),
+                null = format_ratio(Some(c.null_ratio)),
+                distinct = format_ratio(c.distinct_ratio),
+                outliers = c.outliers.map(|o| o.to_string()).unwrap_or_default(),
+            );
+        }
+        html.push_str("</table>\n");
+
+        for (i, c) in self.columns.iter().enumerate() {
+            let _ = writeln!(
+                html,
+                "<section id=\"col-{i}\">\n<h2>{}</h2>\n<p class=\"meta\">{} &middot; {}</p>",
+                escape(&c.name),
+                escape(&c.data_type),
+                kind_name(c.kind)
+            );
+            html.push_str("<div class=\"row\">\n<table>\n");
+            let _ = writeln!(
+                html,
+                "<tr><th>nulls</th><td>{} ({})</td></tr>",
+                c.null_count,
+                format_ratio(Some(c.null_ratio))
+            );
+            if let Some(d) = c.distinct_count {
+                let _ = writeln!(
+                    html,
+                    "<tr><th>distinct</th><td>{} ({})</td></tr>",
+                    d,
+                    format_ratio(c.distinct_ratio)
+                );
+            }
+            if let Some(o) = c.outliers {
+                let _ = writeln!(html, "<tr><th>outliers (IQR)</th><td>{}</td></tr>", o);
+            }
+            for stat in c.stats.iter() {
+                let _ = writeln!(
+                    html,
+                    "<tr><th>{}</th><td>{}</td></tr>",
+                    escape(&stat.name),
+                    escape(stat.value.as_deref().unwrap_or(""))
+                );
+            }
+            html.push_str("</table>\n");
+
+            if !c.histogram.is_empty() {
+                let title = match c.kind.by_length() {
+                    true => "length histogram",
+                    false => "histogram",
+                };
+                let max = c.histogram.iter().map(|b| b.count).max().unwrap_or(0);
+                let _ = writeln!(
+                    html,
+                    "<table class=\"bars\">\n<tr><th colspan=\"3\">{title}</th></tr>"
+                );
+                for bin in c.histogram.iter() {
+                    let _ = writeln!(
+                        html,
+                        "<tr><td>{} &ndash; {}</td><td class=\"bar\"><div style=\"width:{:.1}%\"></div></td><td>{}</td></tr>",
+                        escape(&bin.lower),
+                        escape(&bin.upper),
+                        ratio(bin.count, max) * 100.0,
+                        bin.count
+                    );
+                }
+                html.push_str("</table>\n");
+            }
+            frequency_table(&mut html, "top values", &c.top_values);
+            frequency_table(&mut html, "patterns", &c.patterns);
+            html.push_str("</div>\n</section>\n");
+        }
+
+        if !self.correlations.is_empty() {
+            html.push_str("<h2>Correlations</h2>\n<table>\n<tr><th>left</th><th>right</th><th>pearson</th></tr>\n");
+            for c in self.correlations.iter() {
+                let value = c.value.unwrap_or_default();
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{}</td><td>{}</td><td style=\"background:rgba({},{:.2})\">{}</td></tr>",
+                    escape(&c.left),
+                    escape(&c.right),
+                    if value >= 0.0 { "70,130,180" } else { "205,92,92" },
+                    value.abs(),
+                    c.value.map(|v| format!("{:.3}", v)).unwrap_or_default()
+                );
+            }
+            html.push_str("</table>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin:0 1.5em 1em 0;vertical-align:top}\
+th,td{border:1px solid #ddd;padding:2px 8px;text-align:left;font-size:13px}\
+th{background:#f4f4f4}.row{display:flex;flex-wrap:wrap;align-items:flex-start}\
+.meta{color:#777}.bar{width:200px}.bar div{background:steelblue;height:12px}\
+section{border-top:1px solid #ccc;margin-top:1.5em}";
+
+fn frequency_table(html: &mut String, title: &str, values: &[Frequency]) {
+    if values.is_empty() {
+        return;
+    }
+    let _ = writeln!(
+        html,
+        "<table class=\"bars\">\n<tr><th colspan=\"3\">{title}</th></tr>"
+    );
+    for v in values.iter() {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td class=\"bar\"><div style=\"width:{:.1}%\"></div></td><td>{} ({})</td></tr>",
+            escape(&v.value),
+            v.ratio * 100.0,
+            v.count,
+            format_ratio(Some(v.ratio))
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+/// replace letters and digits by their class, e.g. `ab12@x.com` -> `aa99@a.aaa`
+fn pattern(text: Expr) -> Expr {
+    let replace =
+        |e: Expr, from: &str, to: &str| regexp_replace(e, lit(from), lit(to), Some(lit("g")));
+    let e = replace(text, "[A-Z]", "A");
+    let e = replace(e, "[a-z]", "a");
+    replace(e, "[0-9]", "9")
+}
+
+fn kind_name(kind: ColumnKind) -> &'static str {
+    match kind {
+        ColumnKind::Numeric => "numeric",
+        ColumnKind::Temporal => "temporal",
+        ColumnKind::String => "string",
+        ColumnKind::List => "list",
+        ColumnKind::Other => "other",
+    }
+}
+
+fn ratio(n: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        n as f64 / total as f64
+    }
+}
+
+fn format_ratio(v: Option<f64>) -> String {
+    v.map(|v| format!("{:.1}%", v * 100.0)).unwrap_or_default()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
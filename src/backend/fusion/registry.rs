@@ -0,0 +1,203 @@
+//! 注册的数据集来源: connect的路径和格式, derive/materialize的查询, 以及注册时间
+//!
+//! `refresh` 按原来的方式重新注册, `list` 会标出派生的数据集和它的查询
+
+use std::{collections::HashMap, fs::File, io::Read, path::Path, sync::Arc};
+
+use anyhow::Result;
+use arrow::{
+    array::{RecordBatch, StringArray, UInt64Array},
+    datatypes::{DataType, Field, Schema},
+};
+use chrono::{DateTime, Local};
+
+use super::footer::ParquetFooter;
+use crate::cli::connect::DatabaseConn;
+
+/// bytes read from the head of a text file to estimate its row count
+const SAMPLE_BYTES: u64 = 64 * 1024;
+
+/// how a dataset was registered
+#[derive(Debug, Clone)]
+pub enum Origin {
+    Connect(DatabaseConn),
+    /// `derive`, a view over the query
+    View(String),
+    /// `materialize`, the query result kept in a `MemTable`
+    Materialized {
+        query: String,
+        rows: u64,
+        bytes: u64,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub origin: Origin,
+    pub registered_at: DateTime<Local>,
+}
+
+/// sources by dataset name, tables created by sql have none
+#[derive(Debug, Default)]
+pub struct Registry(HashMap<String, Source>);
+
+impl Source {
+    pub fn new(origin: Origin) -> Self {
+        Self {
+            origin,
+            registered_at: Local::now(),
+        }
+    }
+
+    /// the file or connection string, `None` for derived datasets
+    pub fn path(&self) -> Option<&str> {
+        match &self.origin {
+            Origin::Connect(
+                DatabaseConn::Postgres(s)
+                | DatabaseConn::Csv(s)
+                | DatabaseConn::Parquet(s)
+                | DatabaseConn::Json(s),
+            ) => Some(s),
+            Origin::View(_) | Origin::Materialized { .. } => None,
+        }
+    }
+
+    /// the defining query of a derived dataset
+    pub fn query(&self) -> Option<&str> {
+        match &self.origin {
+            Origin::Connect(_) => None,
+            Origin::View(query) | Origin::Materialized { query, .. } => Some(query),
+        }
+    }
+
+    pub fn format(&self) -> &'static str {
+        match &self.origin {
+            Origin::Connect(DatabaseConn::Postgres(_)) => "postgres",
+            Origin::Connect(DatabaseConn::Csv(_)) => "csv",
+            Origin::Connect(DatabaseConn::Parquet(_)) => "parquet",
+            Origin::Connect(DatabaseConn::Json(_)) => "ndjson",
+            Origin::View(_) => "view",
+            Origin::Materialized { .. } => "memory",
+        }
+    }
+
+    /// exact for parquet (from the footer) and materialized results, extrapolated from the
+    /// first lines for text files
+    pub fn estimated_rows(&self) -> Option<u64> {
+        match &self.origin {
+            Origin::Connect(DatabaseConn::Parquet(path)) => ParquetFooter::try_new(path)
+                .ok()
+                .map(|footer| footer.metadata.file_metadata().num_rows() as u64),
+            Origin::Connect(DatabaseConn::Csv(path)) => {
+                estimate_lines(Path::new(path)).map(|n| n.saturating_sub(1))
+            }
+            Origin::Connect(DatabaseConn::Json(path)) => estimate_lines(Path::new(path)),
+            Origin::Materialized { rows, .. } => Some(*rows),
+            Origin::Connect(DatabaseConn::Postgres(_)) | Origin::View(_) => None,
+        }
+    }
+
+    /// memory held by a materialized dataset
+    pub fn memory(&self) -> Option<String> {
+        match &self.origin {
+            Origin::Materialized { bytes, .. } => Some(human_bytes(*bytes)),
+            _ => None,
+        }
+    }
+}
+
+impl Registry {
+    pub fn get(&self, name: &str) -> Option<&Source> {
+        self.0.get(name)
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, source: Source) {
+        self.0.insert(name.into(), source);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Source> {
+        self.0.remove(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Source)> {
+        self.0.iter()
+    }
+
+    /// one row per registered source, joined with `information_schema.tables` by `list`
+    pub fn to_record_batch(&self) -> Result<RecordBatch> {
+        let mut sources = self.0.iter().collect::<Vec<_>>();
+        sources.sort_by(|a, b| a.0.cmp(b.0));
+        let schema = Schema::new(vec![
+            Field::new("name", DataType::Utf8, false),
+            Field::new("source", DataType::Utf8, true),
+            Field::new("format", DataType::Utf8, false),
+            Field::new("derived_from", DataType::Utf8, true),
+            Field::new("estimated_rows", DataType::UInt64, true),
+            Field::new("memory", DataType::Utf8, true),
+            Field::new("registered_at", DataType::Utf8, false),
+        ]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(StringArray::from_iter_values(
+                    sources.iter().map(|(name, _)| name.as_str()),
+                )),
+                Arc::new(StringArray::from_iter(
+                    sources.iter().map(|(_, s)| s.path()),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    sources.iter().map(|(_, s)| s.format()),
+                )),
+                Arc::new(StringArray::from_iter(
+                    sources.iter().map(|(_, s)| s.query()),
+                )),
+                Arc::new(UInt64Array::from_iter(
+                    sources.iter().map(|(_, s)| s.estimated_rows()),
+                )),
+                Arc::new(StringArray::from_iter(
+                    sources.iter().map(|(_, s)| s.memory()),
+                )),
+                Arc::new(StringArray::from_iter_values(sources.iter().map(
+                    |(_, s)| s.registered_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                ))),
+            ],
+        )?;
+        Ok(batch)
+    }
+}
+
+/// number of lines, counted on the first `SAMPLE_BYTES` and scaled by the file size
+fn estimate_lines(path: &Path) -> Option<u64> {
+    let file = File::open(path).ok()?;
+    let size = file.metadata().ok()?.len();
+    let mut head = vec![];
+    file.take(SAMPLE_BYTES).read_to_end(&mut head).ok()?;
+    if head.is_empty() {
+        return Some(0);
+    }
+    let mut lines = head.iter().filter(|b| **b == b'\n').count() as u64;
+    // the last line may not end with a newline
+    if head.last() != Some(&b'\n') {
+        lines += 1;
+    }
+    let sampled = head.len() as u64;
+    if sampled == size {
+        return Some(lines);
+    }
+    Some((lines as f64 * size as f64 / sampled as f64).round() as u64)
+}
+
+/// e.g. `1.5 MiB`
+pub(crate) fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} B", bytes),
+        _ => format!("{:.1} {}", size, UNITS[unit]),
+    }
+}
@@ -0,0 +1,184 @@
+//! 可复现的随机抽样: 用每一行的值的hash和seed经过splitmix64混合得到[0, 1)之间的伪随机数,
+//! 同样的seed和数据总是得到同样的样本
+//!
+//! 多个partition合并后行的顺序不固定, 所以不能用row_number; 完全相同的行hash也相同,
+//! 会一起被抽中或者一起被跳过
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use arrow::{array::UInt64Array, datatypes::DataType};
+use datafusion::{
+    common::hash_utils::create_hashes,
+    logical_expr::{create_udf, ColumnarValue, Volatility},
+    prelude::{cast, col, ident, lit, DataFrame, Expr},
+};
+
+const ROW_NUMBER: &str = "__rn";
+const UNIFORM: &str = "__u";
+
+/// a random seed, used when `--seed` is not given
+pub(crate) fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// keep each row with probability `fraction`
+pub(crate) fn sample_fraction(df: DataFrame, fraction: f64, seed: u64) -> Result<DataFrame> {
+    let columns = column_names(&df);
+    let df = with_uniform(df, seed)?
+        .filter(col(UNIFORM).lt(lit(fraction)))?
+        .select_columns(&columns.iter().map(|c| c.as_str()).collect::<Vec<_>>())?;
+    Ok(df)
+}
+
+/// `n` rows drawn without replacement, kept in their original order
+pub(crate) fn sample_n(df: DataFrame, n: usize, seed: u64) -> Result<DataFrame> {
+    let columns = column_names(&df);
+    let df = with_uniform(df, seed)?
+        .sort(vec![col(UNIFORM).sort(true, false)])?
+        .limit(0, Some(n))?
+        .sort(vec![col(ROW_NUMBER).sort(true, false)])?
+        .select_columns(&columns.iter().map(|c| c.as_str()).collect::<Vec<_>>())?;
+    Ok(df)
+}
+
+fn column_names(df: &DataFrame) -> Vec<String> {
+    df.schema()
+        .fields()
+        .iter()
+        .map(|f| f.name().clone())
+        .collect()
+}
+
+/// add the row number and a uniform value in [0, 1) derived from the row values, the row
+/// number only keeps the order, it depends on how the partitions are merged
+fn with_uniform(df: DataFrame, seed: u64) -> Result<DataFrame> {
+    let row_number = df.parse_sql_expr("row_number() over ()")?;
+    let hash = row_hash(&df);
+    let df = df.with_column(ROW_NUMBER, row_number)?;
+    let df = df.with_column(UNIFORM, uniform(hash.unwrap_or(col(ROW_NUMBER)), seed))?;
+    Ok(df)
+}
+
+/// hash of the values of the columns which can be hashed, `None` if there are none
+fn row_hash(df: &DataFrame) -> Option<Expr> {
+    let fields = df
+        .schema()
+        .fields()
+        .iter()
+        .filter(|f| hashable(f.data_type()))
+        .collect::<Vec<_>>();
+    if fields.is_empty() {
+        return None;
+    }
+    let udf = create_udf(
+        "row_hash",
+        fields.iter().map(|f| f.data_type().clone()).collect(),
+        Arc::new(DataType::UInt64),
+        Volatility::Immutable,
+        Arc::new(|args| {
+            let arrays = ColumnarValue::values_to_arrays(args)?;
+            let mut hashes = vec![0; arrays[0].len()];
+            // fixed seeds, the hash of a row must be the same in every run
+            let state = ahash::RandomState::with_seeds(0, 0, 0, 0);
+            create_hashes(&arrays, &state, &mut hashes)?;
+            Ok(ColumnarValue::Array(Arc::new(UInt64Array::from(hashes))))
+        }),
+    );
+    Some(udf.call(fields.iter().map(|f| ident(f.name())).collect()))
+}
+
+/// types supported by `create_hashes`
+fn hashable(dt: &DataType) -> bool {
+    match dt {
+        DataType::Struct(fields) => fields.iter().all(|f| hashable(f.data_type())),
+        DataType::List(item) | DataType::LargeList(item) | DataType::FixedSizeList(item, _) => {
+            hashable(item.data_type())
+        }
+        DataType::Dictionary(_, value) => hashable(value),
+        dt => {
+            dt.is_primitive()
+                || matches!(
+                    dt,
+                    DataType::Null
+                        | DataType::Boolean
+                        | DataType::Utf8
+                        | DataType::LargeUtf8
+                        | DataType::Binary
+                        | DataType::LargeBinary
+                        | DataType::FixedSizeBinary(_)
+                )
+        }
+    }
+}
+
+/// splitmix64 of `seed + i * golden`, `i` is the row hash or number, the top 53 bits as a float, integer ops wrap around
+fn uniform(i: Expr, seed: u64) -> Expr {
+    let x = lit(seed) + cast(i, DataType::UInt64) * lit(0x9e37_79b9_7f4a_7c15_u64);
+    let z = (x.clone() ^ (x >> lit(30_u64))) * lit(0xbf58_476d_1ce4_e5b9_u64);
+    let z = (z.clone() ^ (z >> lit(27_u64))) * lit(0x94d0_49bb_1331_11eb_u64);
+    let z = z.clone() ^ (z >> lit(31_u64));
+    cast(z >> lit(11_u64), DataType::Float64) / lit((1_u64 << 53) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{AsArray, Int64Array, RecordBatch};
+    use arrow::datatypes::Int64Type;
+    use datafusion::prelude::SessionContext;
+
+    fn numbers(n: i64) -> Result<DataFrame> {
+        let values = Arc::new(Int64Array::from_iter_values(0..n));
+        let batch = RecordBatch::try_from_iter([("v", values as _)])?;
+        Ok(SessionContext::new().read_batch(batch)?)
+    }
+
+    async fn values(df: DataFrame) -> Result<Vec<i64>> {
+        let batches = df.collect().await?;
+        Ok(batches
+            .iter()
+            .flat_map(|b| b.column(0).as_primitive::<Int64Type>().values().to_vec())
+            .collect())
+    }
+
+    #[tokio::test]
+    async fn sample_n_should_be_reproducible_with_a_seed() -> Result<()> {
+        let first = values(sample_n(numbers(1000)?, 10, 42)?).await?;
+        assert_eq!(first.len(), 10);
+        // rows keep their original order
+        assert!(first.windows(2).all(|w| w[0] < w[1]), "{:?}", first);
+        assert_eq!(first, values(sample_n(numbers(1000)?, 10, 42)?).await?);
+        assert_ne!(first, values(sample_n(numbers(1000)?, 10, 7)?).await?);
+        // asking for more rows than there are gives all of them
+        assert_eq!(
+            values(sample_n(numbers(5)?, 10, 42)?).await?,
+            [0, 1, 2, 3, 4]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sample_fraction_should_keep_about_the_fraction() -> Result<()> {
+        let first = values(sample_fraction(numbers(10_000)?, 0.1, 42)?).await?;
+        assert!((900..1100).contains(&first.len()), "{}", first.len());
+        assert_eq!(
+            first,
+            values(sample_fraction(numbers(10_000)?, 0.1, 42)?).await?
+        );
+        assert!(values(sample_fraction(numbers(100)?, 0.0, 42)?)
+            .await?
+            .is_empty());
+        assert_eq!(
+            values(sample_fraction(numbers(100)?, 1.0, 42)?)
+                .await?
+                .len(),
+            100
+        );
+        Ok(())
+    }
+}
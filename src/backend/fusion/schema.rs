@@ -0,0 +1,160 @@
+//! schema的展示: 表格形式(和DESCRIBE一致)或者树形展示嵌套结构,
+//! parquet数据集还会展示每个文件footer里的信息
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use arrow::{
+    array::{ArrayRef, RecordBatch, StringArray},
+    datatypes::{DataType, Field, Fields, Schema},
+};
+
+use super::footer::ParquetFooter;
+
+#[derive(Debug)]
+pub enum SchemaView {
+    Table(RecordBatch),
+    Tree(String),
+    Parquet(RecordBatch, Vec<ParquetFooter>),
+}
+
+/// one row per column, in the same layout as `DESCRIBE`,
+/// field metadata is shown in an extra column if there is any
+pub fn schema_table(schema: &Schema) -> Result<RecordBatch> {
+    let fields = schema.fields();
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            fields.iter().map(|f| f.name().clone()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            fields.iter().map(|f| f.data_type().to_string()),
+        )),
+        Arc::new(StringArray::from_iter_values(fields.iter().map(
+            |f| match f.is_nullable() {
+                true => "YES",
+                false => "NO",
+            },
+        ))),
+    ];
+    let mut schema_fields = vec![
+        Field::new("column_name", DataType::Utf8, false),
+        Field::new("data_type", DataType::Utf8, false),
+        Field::new("is_nullable", DataType::Utf8, false),
+    ];
+    if fields.iter().any(|f| !f.metadata().is_empty()) {
+        let metadata = fields.iter().map(|f| {
+            let mut kv: Vec<String> = f
+                .metadata()
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect();
+            kv.sort();
+            kv.join(", ")
+        });
+        columns.push(Arc::new(StringArray::from_iter_values(metadata)));
+        schema_fields.push(Field::new("metadata", DataType::Utf8, false));
+    }
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(schema_fields)),
+        columns,
+    )?)
+}
+
+/// nested columns rendered as a tree, fields of structs (also inside lists) are children
+pub fn schema_tree(name: &str, schema: &Schema) -> String {
+    let mut lines = vec![name.to_string()];
+    tree_lines(schema.fields(), "", &mut lines);
+    lines.join("\n")
+}
+
+fn tree_lines(fields: &Fields, prefix: &str, lines: &mut Vec<String>) {
+    for (i, field) in fields.iter().enumerate() {
+        let last = i + 1 == fields.len();
+        let (branch, indent) = match last {
+            true => ("└── ", "    "),
+            false => ("├── ", "│   "),
+        };
+        let nullable = match field.is_nullable() {
+            true => "",
+            false => " NOT NULL",
+        };
+        lines.push(format!(
+            "{}{}{}: {}{}",
+            prefix,
+            branch,
+            field.name(),
+            type_name(field.data_type()),
+            nullable
+        ));
+        if let Some(children) = children(field.data_type()) {
+            tree_lines(children, &format!("{}{}", prefix, indent), lines);
+        }
+    }
+}
+
+fn children(dt: &DataType) -> Option<&Fields> {
+    match dt {
+        DataType::Struct(fields) => Some(fields),
+        DataType::List(item) | DataType::LargeList(item) => children(item.data_type()),
+        _ => None,
+    }
+}
+
+/// short type names, children of nested types are shown in the tree instead
+fn type_name(dt: &DataType) -> String {
+    match dt {
+        DataType::Struct(_) => "Struct".to_string(),
+        DataType::List(item) | DataType::LargeList(item) => {
+            format!("List<{}>", type_name(item.data_type()))
+        }
+        dt => dt.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::util::display::array_value_to_string;
+    use datafusion::prelude::{ParquetReadOptions, SessionContext};
+
+    async fn fixture() -> Result<Schema> {
+        let ctx = SessionContext::new();
+        let df = ctx
+            .read_parquet("assets/fixture.parquet", ParquetReadOptions::default())
+            .await?;
+        Ok(df.schema().as_arrow().clone())
+    }
+
+    #[tokio::test]
+    async fn schema_table_should_show_types_and_nullability() -> Result<()> {
+        let table = schema_table(&fixture().await?)?;
+        let rows: Vec<String> = (0..table.num_rows())
+            .map(|r| {
+                (0..3)
+                    .map(|c| array_value_to_string(table.column(c), r).unwrap())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        assert_eq!(rows[0], "id Int64 NO");
+        assert_eq!(rows[1], "name Utf8 YES");
+        assert_eq!(rows[2], "born Date32 YES");
+        assert_eq!(rows[3], r#"at Timestamp(Millisecond, Some("UTC")) YES"#);
+        assert_eq!(rows[4], "price Decimal128(10, 2) YES");
+        // no field has metadata
+        assert_eq!(table.num_columns(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn schema_tree_should_nest_struct_and_list_fields() -> Result<()> {
+        let tree = schema_tree("fixture", &fixture().await?);
+        let lines: Vec<&str> = tree.lines().collect();
+        assert_eq!(lines[0], "fixture");
+        assert_eq!(lines[1], "├── id: Int64 NOT NULL");
+        assert_eq!(lines[6], "├── address: Struct");
+        assert_eq!(lines[7], "│   └── city: Utf8");
+        assert_eq!(lines[8], "└── tags: List<Utf8>");
+        Ok(())
+    }
+}
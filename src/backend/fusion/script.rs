@@ -0,0 +1,127 @@
+//! 多条语句的sql: 按 `;` 切分(字符串和注释里的不算), 依次执行,
+//! 只显示最后一条的结果, `--all` 时每条结果前面带上语句本身
+
+use anyhow::{Context, Result};
+use datafusion::sql::sqlparser::{
+    dialect::GenericDialect,
+    tokenizer::{Location, Token, Tokenizer},
+};
+
+use super::output::Rows;
+
+/// results of several statements, the last one is executed when shown
+#[derive(Debug)]
+pub struct Script {
+    /// rendered results of the statements before the last one, with `--all`
+    pub outputs: Vec<String>,
+    /// the last statement, shown above its result with `--all`
+    pub header: Option<String>,
+    pub last: Rows,
+    pub statements: usize,
+}
+
+impl Script {
+    pub async fn render(&self) -> Result<String> {
+        let last = self.last.render().await.with_context(|| {
+            format!(
+                "statement {} of {} failed",
+                self.statements, self.statements
+            )
+        })?;
+        let mut outputs = self.outputs.clone();
+        match &self.header {
+            Some(header) => outputs.push(format!("{}\n{}", header, last)),
+            None => outputs.push(last),
+        }
+        Ok(outputs.join("\n"))
+    }
+}
+
+/// statements of a script, `DFParser` can't be used on the whole text as
+/// `CREATE EXTERNAL TABLE` swallows the `;` that ends it; the statements are sliced from
+/// the text as written, rendering the tokens back would lose e.g. the `''` escapes
+pub fn split_statements(sql: &str) -> Result<Vec<String>> {
+    let tokens = Tokenizer::new(&GenericDialect {}, sql).tokenize_with_location()?;
+    let mut statements = vec![];
+    // start of the current statement, and whether it has more than whitespace and comments
+    let (mut start, mut blank) = (0, true);
+    for token in tokens.iter() {
+        match token.token {
+            Token::SemiColon => {
+                let end = offset(sql, &token.location);
+                if !blank {
+                    statements.push(sql[start..end].trim().to_string());
+                }
+                (start, blank) = (end + 1, true);
+            }
+            Token::Whitespace(_) => {}
+            _ => blank = false,
+        }
+    }
+    if !blank {
+        statements.push(sql[start..].trim().to_string());
+    }
+    Ok(statements)
+}
+
+/// byte offset of a token location, lines and columns start at 1 and columns count chars
+fn offset(sql: &str, location: &Location) -> usize {
+    let line_start: usize = sql
+        .split_inclusive('\n')
+        .take(location.line as usize - 1)
+        .map(str::len)
+        .sum();
+    let column: usize = sql[line_start..]
+        .chars()
+        .take(location.column as usize - 1)
+        .map(char::len_utf8)
+        .sum();
+    line_start + column
+}
+
+/// the statement on one line without its comments, shown above its result with `--all`
+pub fn header(statement: &str) -> String {
+    let lines: Vec<&str> = statement
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("--"))
+        .collect();
+    format!("-- {}", lines.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_statements_should_keep_escaped_quotes() {
+        assert_eq!(
+            split_statements("select 'it''s', \"a \"\"b\"\"\" from t; select 2").unwrap(),
+            vec!["select 'it''s', \"a \"\"b\"\"\" from t", "select 2"]
+        );
+    }
+
+    #[test]
+    fn split_statements_should_skip_semicolons_in_strings_and_comments() {
+        let sql = "select ';' as a, '用户;' as b;\n-- the next one; with a comment\nselect 2 /* ; */;\n;  -- trailing";
+        assert_eq!(
+            split_statements(sql).unwrap(),
+            vec![
+                "select ';' as a, '用户;' as b",
+                "-- the next one; with a comment\nselect 2 /* ; */"
+            ]
+        );
+    }
+
+    #[test]
+    fn split_statements_should_end_create_external_table() {
+        let sql = "CREATE EXTERNAL TABLE j STORED AS CSV LOCATION 'assets/juventus.csv' OPTIONS ('has_header' 'true');\nselect count(*) from j";
+        assert_eq!(
+            split_statements(sql).unwrap(),
+            vec![
+                "CREATE EXTERNAL TABLE j STORED AS CSV LOCATION 'assets/juventus.csv' OPTIONS ('has_header' 'true')",
+                "select count(*) from j"
+            ]
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! 每条命令的执行统计, `timing on` 时显示在结果下面
+//!
+//! 物理计划由 `RecordingPlanner` 记录(同时记下规划耗时), 执行完之后从计划的metrics里
+//! 取行数和扫描的字节数; 峰值内存来自 `SessionMemoryPool`
+//!
+//! 规划耗时包括sql到逻辑计划, analyzer/optimizer和物理计划: analyzer的第一条规则
+//! `PlanningStart` 记下开始的时间, sql的解析由 `record_logical` 单独加上
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use datafusion::{
+    config::ConfigOptions,
+    error::Result,
+    execution::context::{QueryPlanner, SessionState},
+    logical_expr::LogicalPlan,
+    optimizer::analyzer::AnalyzerRule,
+    physical_plan::ExecutionPlan,
+    physical_planner::{DefaultPhysicalPlanner, PhysicalPlanner},
+};
+
+use super::registry::human_bytes;
+
+/// a physical plan and how long planning it took
+pub type TimedPlan = (Arc<dyn ExecutionPlan>, Duration);
+
+/// the default physical planner, remembering every plan it creates and how long it took
+/// from the analyzer on while enabled
+#[derive(Debug, Default)]
+pub struct RecordingPlanner {
+    enabled: AtomicBool,
+    /// when the analyzer started on the plan being planned
+    started: Mutex<Option<Instant>>,
+    /// time spent turning sql into logical plans
+    logical: Mutex<Duration>,
+    plans: Mutex<Vec<TimedPlan>>,
+}
+
+/// the first analyzer rule, marks the start of planning a logical plan for `RecordingPlanner`
+#[derive(Debug)]
+pub struct PlanningStart(pub Arc<RecordingPlanner>);
+
+#[derive(Debug)]
+pub struct ExecStats {
+    /// output rows of the query, `None` if the command ran several
+    pub rows: Option<usize>,
+    pub queries: usize,
+    pub wall: Duration,
+    pub planning: Duration,
+    pub bytes_scanned: Option<usize>,
+    pub peak_memory: usize,
+}
+
+impl RecordingPlanner {
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        self.take();
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// add the time spent planning sql into a logical plan
+    pub fn record_logical(&self, elapsed: Duration) {
+        if self.is_enabled() {
+            *self.logical.lock().unwrap() += elapsed;
+        }
+    }
+
+    /// plans created since the last call, and the time spent on logical plans of sql
+    pub fn take(&self) -> (Vec<TimedPlan>, Duration) {
+        let plans = std::mem::take(&mut *self.plans.lock().unwrap());
+        let logical = std::mem::take(&mut *self.logical.lock().unwrap());
+        (plans, logical)
+    }
+}
+
+impl AnalyzerRule for PlanningStart {
+    fn analyze(&self, plan: LogicalPlan, _config: &ConfigOptions) -> Result<LogicalPlan> {
+        *self.0.started.lock().unwrap() = Some(Instant::now());
+        Ok(plan)
+    }
+
+    fn name(&self) -> &str {
+        "planning_start"
+    }
+}
+
+#[async_trait]
+impl QueryPlanner for RecordingPlanner {
+    async fn create_physical_plan(
+        &self,
+        logical_plan: &LogicalPlan,
+        session_state: &SessionState,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        // the plan was analyzed and optimized right before
+        let start = self
+            .started
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap_or_else(Instant::now);
+        let plan = DefaultPhysicalPlanner::default()
+            .create_physical_plan(logical_plan, session_state)
+            .await?;
+        if self.is_enabled() {
+            self.plans
+                .lock()
+                .unwrap()
+                .push((plan.clone(), start.elapsed()));
+        }
+        Ok(plan)
+    }
+}
+
+impl ExecStats {
+    pub fn new(plans: &[TimedPlan], logical: Duration, wall: Duration, peak_memory: usize) -> Self {
+        let rows = match plans {
+            [(plan, _)] => plan.metrics().and_then(|m| m.output_rows()),
+            _ => None,
+        };
+        let bytes_scanned = plans
+            .iter()
+            .filter_map(|(plan, _)| bytes_scanned(plan))
+            .reduce(|a, b| a + b);
+        Self {
+            rows,
+            queries: plans.len(),
+            wall,
+            planning: logical + plans.iter().map(|(_, d)| *d).sum::<Duration>(),
+            bytes_scanned,
+            peak_memory,
+        }
+    }
+
+    /// e.g. `5 rows in 12.31ms (planning 1.02ms, execution 11.29ms), scanned 9.2 KiB, peak memory 0 B`
+    pub fn render(&self) -> String {
+        let count = match (self.rows, self.queries) {
+            (Some(1), _) => "1 row".to_string(),
+            (Some(rows), _) => format!("{} rows", rows),
+            (None, 1) => "1 query".to_string(),
+            (None, n) => format!("{} queries", n),
+        };
+        let execution = self.wall.saturating_sub(self.planning);
+        let mut footer = format!(
+            "{} in {:.2?} (planning {:.2?}, execution {:.2?})",
+            count, self.wall, self.planning, execution
+        );
+        if let Some(bytes) = self.bytes_scanned {
+            footer.push_str(&format!(", scanned {}", human_bytes(bytes as u64)));
+        }
+        footer.push_str(&format!(
+            ", peak memory {}",
+            human_bytes(self.peak_memory as u64)
+        ));
+        footer
+    }
+}
+
+/// `bytes_scanned` of every scan in the plan, only parquet reports it
+fn bytes_scanned(plan: &Arc<dyn ExecutionPlan>) -> Option<usize> {
+    let own = plan
+        .metrics()
+        .and_then(|m| m.sum_by_name("bytes_scanned"))
+        .map(|v| v.as_usize());
+    plan.children()
+        .into_iter()
+        .filter_map(bytes_scanned)
+        .chain(own)
+        .reduce(|a, b| a + b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(rows: Option<usize>, queries: usize, bytes_scanned: Option<usize>) -> ExecStats {
+        ExecStats {
+            rows,
+            queries,
+            wall: Duration::from_millis(12),
+            planning: Duration::from_millis(2),
+            bytes_scanned,
+            peak_memory: 1536,
+        }
+    }
+
+    #[test]
+    fn render_should_split_planning_and_execution() {
+        assert_eq!(
+            stats(Some(5), 1, Some(9420)).render(),
+            "5 rows in 12.00ms (planning 2.00ms, execution 10.00ms), scanned 9.2 KiB, peak memory 1.5 KiB"
+        );
+        // nothing is scanned from memory, rows are only known for a single query
+        assert_eq!(
+            stats(Some(1), 1, None).render(),
+            "1 row in 12.00ms (planning 2.00ms, execution 10.00ms), peak memory 1.5 KiB"
+        );
+        assert!(stats(None, 3, None).render().starts_with("3 queries in"));
+        assert!(stats(None, 1, None).render().starts_with("1 query in"));
+    }
+
+    #[test]
+    fn execution_should_not_be_negative() {
+        let mut stats = stats(Some(5), 1, None);
+        stats.planning = Duration::from_millis(20);
+        assert!(
+            stats.render().contains("execution 0.00ns"),
+            "{}",
+            stats.render()
+        );
+    }
+}
@@ -0,0 +1,617 @@
+//! 用WebAssembly写的函数: `udf load` 用wasmi加载一个.wasm模块, 把导出的函数注册成sql的标量函数.
+//! 模块跑在沙箱里: 不能有导入(没有文件, 网络和时钟), 每次调用都有燃料(fuel)上限,
+//! 死循环会报错而不是卡住repl.
+//! 数字类型直接对应i32/i64/f32/f64; utf8参数需要模块导出 `memory` 和 `alloc(len: i32) -> i32`,
+//! 以(ptr, len)两个i32传入, utf8结果是一个i64, 高32位是ptr, 低32位是len;
+//! 导出了 `dealloc(ptr: i32, len: i32)` 时, 用完的字符串会交还给模块.
+//! 参数有null时结果是null, 不会调用函数
+
+use std::{
+    any::Any,
+    fmt,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, bail, Context};
+use arrow::{
+    array::{new_empty_array, ArrayRef},
+    compute::can_cast_types,
+    datatypes::DataType,
+};
+use datafusion::{
+    common::plan_err,
+    error::{DataFusionError, Result},
+    logical_expr::{ColumnarValue, ScalarUDF, ScalarUDFImpl, Signature, Volatility},
+    scalar::ScalarValue,
+};
+use wasmi::{
+    core::{ValType, F32, F64},
+    Config, Engine, Func, Instance, Linker, Memory, Module, Store, TypedFunc, Val,
+};
+
+use super::{
+    functions::on_arrays,
+    plugin::{PluginFunction, UdfPlugin},
+};
+use crate::cli::udf::WasmType;
+
+/// instructions a call may run, including `alloc` and `dealloc` of its strings
+const FUEL: u64 = 10_000_000;
+
+/// exports used to pass strings, not loaded as functions
+const HELPERS: &[&str] = &["alloc", "dealloc"];
+
+/// how to call an exported function from sql
+#[derive(Debug, Clone)]
+pub struct WasmSignature {
+    pub name: String,
+    pub export: String,
+    pub args: Vec<WasmType>,
+    pub returns: WasmType,
+}
+
+/// the functions of a loaded module, a plugin named after the file
+pub struct WasmPlugin {
+    name: String,
+    module: Arc<Mutex<WasmModule>>,
+    functions: Vec<(WasmSignature, Func)>,
+}
+
+/// an exported function as a sql function; unlike `create_udf` its arguments are cast to the
+/// declared types, e.g. the literal 3 (Int64) for an i32 argument
+struct WasmUdf {
+    signature: WasmSignature,
+    sql: Signature,
+    module: Arc<Mutex<WasmModule>>,
+    func: Func,
+}
+
+/// an instance of a module, calls are serialized since they share its memory
+struct WasmModule {
+    store: Store<()>,
+    memory: Option<Memory>,
+    alloc: Option<TypedFunc<i32, i32>>,
+    dealloc: Option<TypedFunc<(i32, i32), ()>>,
+}
+
+impl WasmType {
+    pub fn data_type(&self) -> DataType {
+        match self {
+            WasmType::Int32 => DataType::Int32,
+            WasmType::Int64 => DataType::Int64,
+            WasmType::Float32 => DataType::Float32,
+            WasmType::Float64 => DataType::Float64,
+            WasmType::Utf8 => DataType::Utf8,
+        }
+    }
+
+    fn params(&self) -> Vec<ValType> {
+        match self {
+            WasmType::Utf8 => vec![ValType::I32, ValType::I32],
+            ty => vec![ty.result()],
+        }
+    }
+
+    fn result(&self) -> ValType {
+        match self {
+            WasmType::Int32 => ValType::I32,
+            WasmType::Int64 | WasmType::Utf8 => ValType::I64,
+            WasmType::Float32 => ValType::F32,
+            WasmType::Float64 => ValType::F64,
+        }
+    }
+
+    fn numeric(ty: &ValType) -> Option<Self> {
+        match ty {
+            ValType::I32 => Some(WasmType::Int32),
+            ValType::I64 => Some(WasmType::Int64),
+            ValType::F32 => Some(WasmType::Float32),
+            ValType::F64 => Some(WasmType::Float64),
+            _ => None,
+        }
+    }
+}
+
+impl WasmPlugin {
+    /// load a module from a file, see `try_new`
+    pub fn load(path: &str, signature: Option<WasmSignature>) -> anyhow::Result<Self> {
+        let wasm = std::fs::read(path).with_context(|| format!("can't read {}", path))?;
+        let name = Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string());
+        Self::try_new(&name, &wasm, signature)
+    }
+
+    /// instantiate a module, with the function of `signature`, or every export taking and
+    /// returning numbers when it is `None`
+    pub fn try_new(
+        name: &str,
+        wasm: &[u8],
+        signature: Option<WasmSignature>,
+    ) -> anyhow::Result<Self> {
+        let engine = Engine::new(Config::default().consume_fuel(true));
+        let module = Module::new(&engine, wasm)
+            .with_context(|| format!("{} is not a valid wasm module", name))?;
+        if let Some(import) = module.imports().next() {
+            bail!(
+                "{} imports {}::{}, wasm functions run without imports",
+                name,
+                import.module(),
+                import.name()
+            );
+        }
+        let mut store = Store::new(&engine, ());
+        store.set_fuel(FUEL).map_err(|e| anyhow!("{}", e))?;
+        let instance = Linker::new(&engine)
+            .instantiate(&mut store, &module)?
+            .start(&mut store)
+            .with_context(|| format!("the start function of {} failed", name))?;
+        let signatures = match signature {
+            Some(signature) => vec![signature],
+            None => numeric_exports(&module),
+        };
+        if signatures.is_empty() {
+            bail!(
+                "{} has no exported function taking and returning numbers, \
+                 load one with --name, --args and --returns",
+                name
+            );
+        }
+        let functions = signatures
+            .into_iter()
+            .map(|signature| {
+                let func = checked_func(&store, &instance, &signature)?;
+                Ok((signature, func))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let strings = functions
+            .iter()
+            .any(|(s, _)| s.args.contains(&WasmType::Utf8));
+        let returns_string = functions.iter().any(|(s, _)| s.returns == WasmType::Utf8);
+        let memory = instance.get_memory(&store, "memory");
+        let alloc = instance.get_typed_func(&store, "alloc").ok();
+        let dealloc = instance.get_typed_func(&store, "dealloc").ok();
+        if (strings || returns_string) && memory.is_none() {
+            bail!("{} must export its memory to pass utf8 values", name);
+        }
+        if strings && alloc.is_none() {
+            bail!(
+                "{} must export alloc(len: i32) -> i32 to take utf8 arguments",
+                name
+            );
+        }
+        let module = WasmModule {
+            store,
+            memory,
+            alloc,
+            dealloc,
+        };
+        Ok(Self {
+            name: name.to_string(),
+            module: Arc::new(Mutex::new(module)),
+            functions,
+        })
+    }
+}
+
+impl UdfPlugin for WasmPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn functions(&self) -> Vec<PluginFunction> {
+        self.functions
+            .iter()
+            .map(|(signature, func)| {
+                let udf = ScalarUDF::from(WasmUdf {
+                    signature: signature.clone(),
+                    sql: Signature::user_defined(Volatility::Immutable),
+                    module: self.module.clone(),
+                    func: *func,
+                });
+                let args = signature
+                    .args
+                    .iter()
+                    .map(|ty| ty.data_type().to_string())
+                    .collect::<Vec<_>>();
+                PluginFunction::scalar(
+                    udf,
+                    format!("{}({})", signature.name, args.join(", ")),
+                    format!(
+                        "{} exported by {}, returns {}",
+                        signature.export,
+                        self.name,
+                        signature.returns.data_type()
+                    ),
+                )
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for WasmUdf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasmUdf")
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl ScalarUDFImpl for WasmUdf {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.signature.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.sql
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(self.signature.returns.data_type())
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        on_arrays(args, |arrays| {
+            invoke(&self.module, self.func, &self.signature, arrays)
+        })
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> Result<Vec<DataType>> {
+        let name = &self.signature.name;
+        if arg_types.len() != self.signature.args.len() {
+            return plan_err!(
+                "{} takes {} argument(s), got {}",
+                name,
+                self.signature.args.len(),
+                arg_types.len()
+            );
+        }
+        arg_types
+            .iter()
+            .zip(self.signature.args.iter().map(|ty| ty.data_type()))
+            .map(|(from, to)| match can_cast_types(from, &to) {
+                true => Ok(to),
+                false => plan_err!("{} can't take {} as {}", name, from, to),
+            })
+            .collect()
+    }
+}
+
+impl WasmModule {
+    /// call `func` on the values of one row, none of them null
+    fn call(
+        &mut self,
+        func: Func,
+        returns: WasmType,
+        values: &[ScalarValue],
+    ) -> anyhow::Result<ScalarValue> {
+        self.store.set_fuel(FUEL).map_err(|e| anyhow!("{}", e))?;
+        let mut params = vec![];
+        let mut strings = vec![];
+        for value in values {
+            match value {
+                ScalarValue::Int32(Some(v)) => params.push(Val::I32(*v)),
+                ScalarValue::Int64(Some(v)) => params.push(Val::I64(*v)),
+                ScalarValue::Float32(Some(v)) => params.push(Val::F32(F32::from_float(*v))),
+                ScalarValue::Float64(Some(v)) => params.push(Val::F64(F64::from_float(*v))),
+                ScalarValue::Utf8(Some(s)) => {
+                    let (ptr, len) = self.write(s.as_bytes())?;
+                    strings.push((ptr, len));
+                    params.extend([Val::I32(ptr), Val::I32(len)]);
+                }
+                value => bail!("unsupported argument of type {}", value.data_type()),
+            }
+        }
+        let mut results = [Val::default(returns.result())];
+        func.call(&mut self.store, &params, &mut results)?;
+        let value = match (returns, &results[0]) {
+            (WasmType::Int32, Val::I32(v)) => ScalarValue::Int32(Some(*v)),
+            (WasmType::Int64, Val::I64(v)) => ScalarValue::Int64(Some(*v)),
+            (WasmType::Float32, Val::F32(v)) => ScalarValue::Float32(Some(v.to_float())),
+            (WasmType::Float64, Val::F64(v)) => ScalarValue::Float64(Some(v.to_float())),
+            (WasmType::Utf8, Val::I64(v)) => {
+                let (ptr, len) = ((*v as u64 >> 32) as i32, *v as u32 as i32);
+                strings.push((ptr, len));
+                ScalarValue::Utf8(Some(self.read(ptr, len)?))
+            }
+            (returns, result) => bail!("expect {:?}, got {:?}", returns, result),
+        };
+        if let Some(dealloc) = self.dealloc {
+            for (ptr, len) in strings {
+                dealloc.call(&mut self.store, (ptr, len))?;
+            }
+        }
+        Ok(value)
+    }
+
+    /// copy bytes into memory given by the module's `alloc`
+    fn write(&mut self, bytes: &[u8]) -> anyhow::Result<(i32, i32)> {
+        let (Some(memory), Some(alloc)) = (self.memory, self.alloc) else {
+            bail!("the module can't take utf8 arguments");
+        };
+        let len = i32::try_from(bytes.len())?;
+        let ptr = alloc.call(&mut self.store, len)?;
+        memory
+            .write(&mut self.store, ptr as u32 as usize, bytes)
+            .map_err(|_| anyhow!("alloc returned memory out of bounds"))?;
+        Ok((ptr, len))
+    }
+
+    fn read(&self, ptr: i32, len: i32) -> anyhow::Result<String> {
+        let memory = self.memory.context("the module can't return utf8")?;
+        let mut bytes = vec![0; len as u32 as usize];
+        memory
+            .read(&self.store, ptr as u32 as usize, &mut bytes)
+            .map_err(|_| anyhow!("the returned string is out of bounds"))?;
+        String::from_utf8(bytes).context("the returned string is not utf8")
+    }
+}
+
+/// call `func` row by row, a row with a null argument is null
+fn invoke(
+    module: &Mutex<WasmModule>,
+    func: Func,
+    signature: &WasmSignature,
+    args: &[ArrayRef],
+) -> Result<ArrayRef> {
+    let rows = args.first().map(|array| array.len()).unwrap_or(1);
+    let returns = signature.returns.data_type();
+    if rows == 0 {
+        return Ok(new_empty_array(&returns));
+    }
+    let mut module = module
+        .lock()
+        .map_err(|_| DataFusionError::Execution("wasm module is poisoned".to_string()))?;
+    let mut out = Vec::with_capacity(rows);
+    for row in 0..rows {
+        let values = args
+            .iter()
+            .map(|array| ScalarValue::try_from_array(array, row))
+            .collect::<Result<Vec<_>>>()?;
+        if values.iter().any(|value| value.is_null()) {
+            out.push(ScalarValue::try_from(&returns)?);
+            continue;
+        }
+        let value = module.call(func, signature.returns, &values).map_err(|e| {
+            DataFusionError::Execution(format!("wasm function {} failed: {:#}", signature.name, e))
+        })?;
+        out.push(value);
+    }
+    ScalarValue::iter_to_array(out)
+}
+
+/// every exported function taking at least one number and returning one, named after the export
+fn numeric_exports(module: &Module) -> Vec<WasmSignature> {
+    let mut signatures = module
+        .exports()
+        .filter(|export| !HELPERS.contains(&export.name()))
+        .filter_map(|export| {
+            let ty = export.ty().func()?;
+            let args = ty
+                .params()
+                .iter()
+                .map(WasmType::numeric)
+                .collect::<Option<Vec<_>>>()?;
+            let returns = match ty.results() {
+                [result] => WasmType::numeric(result)?,
+                _ => return None,
+            };
+            (!args.is_empty()).then(|| WasmSignature {
+                name: export.name().to_string(),
+                export: export.name().to_string(),
+                args,
+                returns,
+            })
+        })
+        .collect::<Vec<_>>();
+    signatures.sort_by(|a, b| a.name.cmp(&b.name));
+    signatures
+}
+
+/// the exported function, if its wasm type matches the declared arguments and result
+fn checked_func(
+    store: &Store<()>,
+    instance: &Instance,
+    signature: &WasmSignature,
+) -> anyhow::Result<Func> {
+    let func = instance
+        .get_func(store, &signature.export)
+        .with_context(|| format!("the module exports no function {}", signature.export))?;
+    let ty = func.ty(store);
+    let params = signature
+        .args
+        .iter()
+        .flat_map(|ty| ty.params())
+        .collect::<Vec<_>>();
+    let results = [signature.returns.result()];
+    if ty.params() != params || ty.results() != results {
+        bail!(
+            "{} is ({}) -> ({}) in the module, but ({}) -> ({}) is expected for {:?} -> {:?}",
+            signature.export,
+            val_types(ty.params()),
+            val_types(ty.results()),
+            val_types(&params),
+            val_types(&results),
+            signature.args,
+            signature.returns
+        );
+    }
+    Ok(func)
+}
+
+fn val_types(types: &[ValType]) -> String {
+    types
+        .iter()
+        .map(|ty| format!("{:?}", ty).to_lowercase())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fusion::plugin::Udf;
+    use arrow::array::{Array, Float64Array, Int64Array, StringArray};
+    use datafusion::prelude::SessionContext;
+
+    const NUMBERS: &str = r#"
+        (module
+          (func (export "add") (param i64 i64) (result i64)
+            local.get 0 local.get 1 i64.add)
+          (func (export "half") (param f64) (result f64)
+            local.get 0 f64.const 2 f64.div)
+          (func (export "spin") (param i32) (result i32)
+            (loop br 0) i32.const 0)
+          (func (export "nothing")))
+    "#;
+
+    /// upper-cases ascii in place, strings come from a bump allocator
+    const STRINGS: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            global.get $next local.set $ptr
+            global.get $next local.get $len i32.add global.set $next
+            local.get $ptr)
+          (func (export "shout") (param $ptr i32) (param $len i32) (result i64)
+            (local $i i32) (local $c i32)
+            (block $done
+              (loop $next
+                local.get $i local.get $len i32.ge_u br_if $done
+                local.get $ptr local.get $i i32.add i32.load8_u local.set $c
+                (if (i32.and
+                      (i32.ge_u (local.get $c) (i32.const 97))
+                      (i32.le_u (local.get $c) (i32.const 122)))
+                  (then
+                    (i32.store8
+                      (i32.add (local.get $ptr) (local.get $i))
+                      (i32.sub (local.get $c) (i32.const 32)))))
+                local.get $i i32.const 1 i32.add local.set $i
+                br $next))
+            local.get $ptr i64.extend_i32_u i64.const 32 i64.shl
+            local.get $len i64.extend_i32_u i64.or))
+    "#;
+
+    fn plugin(wat: &str, signature: Option<WasmSignature>) -> anyhow::Result<WasmPlugin> {
+        WasmPlugin::try_new("test.wasm", &wat::parse_str(wat)?, signature)
+    }
+
+    fn context(plugin: &WasmPlugin) -> SessionContext {
+        let ctx = SessionContext::new();
+        for function in plugin.functions() {
+            if let Udf::Scalar(udf) = function.udf {
+                ctx.register_udf(udf);
+            }
+        }
+        ctx
+    }
+
+    async fn column(ctx: &SessionContext, sql: &str) -> anyhow::Result<ArrayRef> {
+        let batches = ctx.sql(sql).await?.collect().await?;
+        Ok(batches[0].column(0).clone())
+    }
+
+    fn shout() -> WasmSignature {
+        WasmSignature {
+            name: "shout".to_string(),
+            export: "shout".to_string(),
+            args: vec![WasmType::Utf8],
+            returns: WasmType::Utf8,
+        }
+    }
+
+    #[tokio::test]
+    async fn numeric_exports_should_be_loaded_and_skip_nulls() -> anyhow::Result<()> {
+        let plugin = plugin(NUMBERS, None)?;
+        let names = plugin
+            .functions()
+            .iter()
+            .map(|f| f.usage.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["add(Int64, Int64)", "half(Float64)", "spin(Int32)"]);
+
+        let ctx = context(&plugin);
+        let sums = column(
+            &ctx,
+            "select add(a, 10) from (values (1), (null), (32)) t(a)",
+        )
+        .await?;
+        let sums = sums.as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(sums.iter().collect::<Vec<_>>(), [Some(11), None, Some(42)]);
+
+        let halves = column(&ctx, "select half(3)").await?;
+        let halves = halves.as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(halves.value(0), 1.5);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn infinite_loops_should_run_out_of_fuel() -> anyhow::Result<()> {
+        let ctx = context(&plugin(NUMBERS, None)?);
+        let err = column(&ctx, "select spin(1)")
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("wasm function spin failed"), "{}", err);
+        assert!(err.contains("fuel"), "{}", err);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn utf8_should_go_through_memory() -> anyhow::Result<()> {
+        let ctx = context(&plugin(STRINGS, Some(shout()))?);
+        let names = column(
+            &ctx,
+            "select shout(a) from (values ('Del Piero'), (null), ('')) t(a)",
+        )
+        .await?;
+        let names = names.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(
+            names.iter().collect::<Vec<_>>(),
+            [Some("DEL PIERO"), None, Some("")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn modules_without_numeric_exports_need_a_signature() -> anyhow::Result<()> {
+        let wat = r#"(module (func (export "shout") (param i32 i32) (result i64) i64.const 0))"#;
+        assert_eq!(plugin(wat, None)?.functions().len(), 1);
+        let err = plugin(r#"(module (func (export "nothing")))"#, None)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("--name, --args and --returns"));
+        Ok(())
+    }
+
+    #[test]
+    fn declared_types_should_match_the_module() {
+        let signature = WasmSignature {
+            returns: WasmType::Int32,
+            ..shout()
+        };
+        let err = plugin(STRINGS, Some(signature)).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "shout is (i32, i32) -> (i64) in the module, but (i32, i32) -> (i32) is expected for [Utf8] -> Int32"
+        );
+    }
+
+    #[test]
+    fn imports_should_be_refused() {
+        let wat = r#"(module (import "env" "now" (func (result i64))))"#;
+        let err = plugin(wat, None).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "test.wasm imports env::now, wasm functions run without imports"
+        );
+    }
+}
@@ -0,0 +1,36 @@
+use super::{dataset_name, parse_dataset_name};
+use crate::{Backend, CmdExector, ReplContext, ReplMsg};
+use clap::{ArgMatches, Parser};
+use reedline_repl_rs::Result;
+
+#[derive(Debug, Parser)]
+pub struct AliasOpts {
+    #[arg(value_parser = parse_dataset_name, help = "Name of the dataset")]
+    pub name: String,
+    #[arg(value_parser = parse_dataset_name, help = "Another name for the dataset")]
+    pub alias: String,
+}
+
+impl AliasOpts {
+    pub fn new(name: String, alias: String) -> Self {
+        AliasOpts { name, alias }
+    }
+}
+
+impl CmdExector for AliasOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        backend.alias(self).await?;
+        Ok(format!("{} is an alias of {}", self.alias, self.name))
+    }
+}
+
+pub fn alias(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let name = dataset_name(&args);
+    let alias = args
+        .get_one::<String>("alias")
+        .expect("expect alias")
+        .to_owned();
+    let (msg, tx) = ReplMsg::new(AliasOpts::new(name, alias));
+    let res = context.send(msg, tx);
+    Ok(res)
+}
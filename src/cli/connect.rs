@@ -1,3 +1,6 @@
+use std::path::Path;
+
+use super::parse_dataset_name;
 use crate::{CmdExector, ReplContext, ReplMsg};
 use clap::{ArgMatches, Parser};
 use reedline_repl_rs::Result;
@@ -15,10 +18,23 @@ pub struct ConnectOpts {
     /// 这里也是help: Connection string to the dataset, could be postgres or local file(support: csv, parquet, json)
     #[arg(value_parser = verify_conn_str)]
     pub conn: DatabaseConn,
+    #[arg(
+        value_parser = parse_dataset_name,
+        help = "Name of the dataset, defaults to the file name (or the table)"
+    )]
+    pub name: Option<String>,
+    /// same as the positional name, e.g. `connect data.csv -n "My Data"`
+    #[arg(
+        id = "name_flag",
+        short = 'n',
+        long = "name",
+        value_parser = parse_dataset_name,
+        conflicts_with = "name",
+        help = "Name of the dataset, quote it to keep spaces and case"
+    )]
+    pub name_flag: Option<String>,
     #[arg(short, long, help = "if database, the name of the table")]
     pub table: Option<String>,
-    #[arg(short, long, help = "Name of the dataset")]
-    pub name: String,
 }
 
 impl CmdExector for ConnectOpts {
@@ -37,8 +53,8 @@ pub fn connect(args: ArgMatches, context: &mut ReplContext) -> Result<Option<Str
     let table = args.get_one::<String>("table").map(|s| s.to_owned());
     let name = args
         .get_one::<String>("name")
-        .expect("expect conn_str")
-        .to_owned();
+        .or_else(|| args.get_one::<String>("name_flag"))
+        .map(|s| s.to_owned());
 
     let cmd = ConnectOpts::new(conn, table, name);
     let (msg, tx) = ReplMsg::new(cmd);
@@ -47,8 +63,30 @@ pub fn connect(args: ArgMatches, context: &mut ReplContext) -> Result<Option<Str
 }
 
 impl ConnectOpts {
-    pub fn new(conn: DatabaseConn, table: Option<String>, name: String) -> Self {
-        ConnectOpts { conn, table, name }
+    pub fn new(conn: DatabaseConn, table: Option<String>, name: Option<String>) -> Self {
+        ConnectOpts {
+            conn,
+            name,
+            name_flag: None,
+            table,
+        }
+    }
+
+    /// the given name, otherwise the file name without extension, e.g. `users` for
+    /// `assets/users.ndjson`
+    pub fn dataset_name(&self) -> String {
+        if let Some(name) = self.name.as_ref().or(self.name_flag.as_ref()) {
+            return name.clone();
+        }
+        match &self.conn {
+            DatabaseConn::Postgres(_) => self.table.clone().unwrap_or_else(|| "postgres".into()),
+            DatabaseConn::Csv(path) | DatabaseConn::Parquet(path) | DatabaseConn::Json(path) => {
+                Path::new(path)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.clone())
+            }
+        }
     }
 }
 
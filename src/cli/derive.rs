@@ -0,0 +1,77 @@
+use super::{dataset_name, parse_dataset_name};
+use crate::{Backend, CmdExector, ReplContext, ReplMsg};
+use clap::{ArgMatches, Parser};
+use reedline_repl_rs::Result;
+
+#[derive(Debug, Parser)]
+pub struct DeriveOpts {
+    #[arg(value_parser = parse_dataset_name, help = "Name of the derived dataset")]
+    pub name: String,
+    #[arg(value_parser = ["as"], value_name = "as", hide = true)]
+    pub keyword: String,
+    #[arg(help = "SQL query defining the dataset")]
+    pub query: String,
+}
+
+#[derive(Debug, Parser)]
+pub struct MaterializeOpts {
+    #[arg(value_parser = parse_dataset_name, help = "Name of the materialized dataset")]
+    pub name: String,
+    #[arg(value_parser = ["as"], value_name = "as", hide = true)]
+    pub keyword: String,
+    #[arg(help = "SQL query whose result is kept in memory")]
+    pub query: String,
+}
+
+impl DeriveOpts {
+    pub fn new(name: String, query: String) -> Self {
+        DeriveOpts {
+            name,
+            keyword: "as".into(),
+            query,
+        }
+    }
+}
+
+impl MaterializeOpts {
+    pub fn new(name: String, query: String) -> Self {
+        MaterializeOpts {
+            name,
+            keyword: "as".into(),
+            query,
+        }
+    }
+}
+
+impl CmdExector for DeriveOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        backend.derive(self).await?;
+        Ok(format!("derived {}", self.name))
+    }
+}
+
+impl CmdExector for MaterializeOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        backend.materialize(self).await?;
+        Ok(format!("materialized {}", self.name))
+    }
+}
+
+pub fn derive(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let (name, query) = (dataset_name(&args), query(&args));
+    let (msg, tx) = ReplMsg::new(DeriveOpts::new(name, query));
+    let res = context.send(msg, tx);
+    Ok(res)
+}
+
+pub fn materialize(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let (name, query) = (dataset_name(&args), query(&args));
+    let (msg, tx) = ReplMsg::new(MaterializeOpts::new(name, query));
+    let res = context.send(msg, tx);
+    Ok(res)
+}
+
+fn query(args: &ArgMatches) -> String {
+    let query = args.get_one::<String>("query").expect("expect query");
+    query.trim().trim_end_matches(';').to_string()
+}
@@ -1,13 +1,42 @@
-use crate::{Backend, CmdExector, ReplContext, ReplDisplay, ReplMsg};
+use super::{dataset_name, parse_dataset_name, timed};
+use crate::{
+    backend::fusion::describe2::{parse_percentile, DescribeMethod},
+    Backend, CmdExector, ReplContext, ReplDisplay, ReplMsg,
+};
 use clap::{ArgMatches, Parser};
 use reedline_repl_rs::Result;
 
 pub fn describe(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
-    let name = args
-        .get_one::<String>("name")
-        .expect("expect name")
-        .to_owned();
-    let opts = DescribeOpts::new(name);
+    let name = dataset_name(&args);
+    let columns = args
+        .get_many::<String>("columns")
+        .map(|v| v.cloned().collect());
+    let stats = args
+        .get_many::<DescribeMethod>("stats")
+        .map(|v| v.copied().collect());
+    let percentiles = args
+        .get_many::<u8>("percentiles")
+        .map(|v| v.copied().collect())
+        .unwrap_or_default();
+    let by = args
+        .get_many::<String>("by")
+        .map(|v| v.cloned().collect())
+        .unwrap_or_default();
+    let top_k = args.get_one::<usize>("top_k").copied().unwrap_or(0);
+    let lists = args.get_flag("lists");
+    let sparkline = args.get_flag("sparkline");
+    let flatten = args.get_one::<usize>("flatten").copied();
+    let opts = DescribeOpts::new(
+        name,
+        columns,
+        stats,
+        percentiles,
+        by,
+        top_k,
+        lists,
+        sparkline,
+        flatten,
+    );
     let (msg, tx) = ReplMsg::new(opts);
     let res = context.send(msg, tx);
     Ok(res)
@@ -15,19 +44,108 @@ pub fn describe(args: ArgMatches, context: &mut ReplContext) -> Result<Option<St
 
 #[derive(Debug, Parser)]
 pub struct DescribeOpts {
-    #[arg(help = "Name of the dataset")]
+    #[arg(value_parser = parse_dataset_name, help = "Name of the dataset")]
     pub name: String,
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        help = "Columns to describe, e.g. a,b"
+    )]
+    pub columns: Option<Vec<String>>,
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        help = "Statistics to compute, e.g. count,mean,p90 (default: count,null_count,mean,std,min,max,median,p25,p50,p75)"
+    )]
+    pub stats: Option<Vec<DescribeMethod>>,
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        value_parser = parse_percentile,
+        help = "Extra percentiles to compute, e.g. 1,5,95"
+    )]
+    pub percentiles: Vec<u8>,
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        help = "Group by columns, statistics are shown per group in long format, e.g. gender"
+    )]
+    pub by: Vec<String>,
+    #[arg(
+        short = 'k',
+        long,
+        default_value_t = 0,
+        help = "Number of most frequent values shown for string columns, each column takes an extra scan"
+    )]
+    pub top_k: usize,
+    #[arg(
+        long,
+        help = "Show statistics of the elements of list columns, each list column takes an extra scan"
+    )]
+    pub lists: bool,
+    #[arg(
+        long,
+        help = "Show a sparkline of the distribution of each numeric column"
+    )]
+    pub sparkline: bool,
+    #[arg(
+        short,
+        long,
+        num_args = 0..=1,
+        default_missing_value = "3",
+        help = "Expand struct columns into dotted leaf columns up to the given depth (default: 3), otherwise structs are skipped"
+    )]
+    pub flatten: Option<usize>,
 }
 
 impl DescribeOpts {
-    pub fn new(name: String) -> Self {
-        DescribeOpts { name }
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        name: String,
+        columns: Option<Vec<String>>,
+        stats: Option<Vec<DescribeMethod>>,
+        percentiles: Vec<u8>,
+        by: Vec<String>,
+        top_k: usize,
+        lists: bool,
+        sparkline: bool,
+        flatten: Option<usize>,
+    ) -> Self {
+        DescribeOpts {
+            name,
+            columns,
+            stats,
+            percentiles,
+            by,
+            top_k,
+            lists,
+            sparkline,
+            flatten,
+        }
+    }
+
+    /// the statistics to compute, `--percentiles` are appended to `--stats`
+    pub fn methods(&self) -> Vec<DescribeMethod> {
+        let mut methods = self.stats.clone().unwrap_or_else(DescribeMethod::defaults);
+        methods.extend(
+            self.percentiles
+                .iter()
+                .map(|p| DescribeMethod::Percentile(*p)),
+        );
+        methods
     }
 }
 
 impl CmdExector for DescribeOpts {
     async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
-        let df = backend.describe(self).await?;
-        df.display().await
+        let backend = &*backend;
+        timed(backend, async {
+            backend.describe(self).await?.display().await
+        })
+        .await
     }
 }
@@ -0,0 +1,54 @@
+use super::{parse_dataset_name, timed};
+use crate::{Backend, CmdExector, ReplContext, ReplDisplay, ReplMsg};
+use clap::{ArgMatches, Parser};
+use reedline_repl_rs::Result;
+
+#[derive(Debug, Parser)]
+pub struct DiffOpts {
+    #[arg(value_parser = parse_dataset_name, help = "The old dataset")]
+    pub a: String,
+    #[arg(value_parser = parse_dataset_name, help = "The new dataset")]
+    pub b: String,
+    #[arg(
+        short,
+        long,
+        required = true,
+        value_delimiter = ',',
+        help = "Columns identifying a row in both datasets, e.g. id or region,id, null keys match each other"
+    )]
+    pub key: Vec<String>,
+    #[arg(
+        short = 'n',
+        long,
+        default_value_t = 10,
+        help = "Number of changed values to show"
+    )]
+    pub limit: usize,
+}
+
+impl DiffOpts {
+    pub fn new(a: String, b: String, key: Vec<String>, limit: usize) -> Self {
+        DiffOpts { a, b, key, limit }
+    }
+}
+
+impl CmdExector for DiffOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        let backend = &*backend;
+        timed(backend, async { backend.diff(self).await?.display().await }).await
+    }
+}
+
+pub fn diff(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let a = args.get_one::<String>("a").expect("expect a").to_owned();
+    let b = args.get_one::<String>("b").expect("expect b").to_owned();
+    let key = args
+        .get_many::<String>("key")
+        .expect("expect key")
+        .cloned()
+        .collect();
+    let limit = args.get_one::<usize>("limit").copied().unwrap_or(10);
+    let (msg, tx) = ReplMsg::new(DiffOpts::new(a, b, key, limit));
+    let res = context.send(msg, tx);
+    Ok(res)
+}
@@ -0,0 +1,47 @@
+use crate::{Backend, CmdExector, ReplContext, ReplDisplay, ReplMsg};
+use clap::{ArgMatches, Parser};
+use reedline_repl_rs::Result;
+
+#[derive(Debug, Parser)]
+pub struct ExplainOpts {
+    #[arg(help = "SQL query to explain")]
+    pub query: String,
+    #[arg(
+        short,
+        long,
+        help = "Run the query and show the metrics of every operator"
+    )]
+    pub analyze: bool,
+    #[arg(short, long, help = "Show plan schemas and every metric")]
+    pub verbose: bool,
+}
+
+impl ExplainOpts {
+    pub fn new(query: String, analyze: bool, verbose: bool) -> Self {
+        ExplainOpts {
+            query,
+            analyze,
+            verbose,
+        }
+    }
+}
+
+impl CmdExector for ExplainOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        backend.explain(self).await?.display().await
+    }
+}
+
+pub fn explain(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let query = args
+        .get_one::<String>("query")
+        .expect("expect query")
+        .trim()
+        .trim_end_matches(';')
+        .to_string();
+    let analyze = args.get_flag("analyze");
+    let verbose = args.get_flag("verbose");
+    let (msg, tx) = ReplMsg::new(ExplainOpts::new(query, analyze, verbose));
+    let res = context.send(msg, tx);
+    Ok(res)
+}
@@ -0,0 +1,18 @@
+use crate::{Backend, CmdExector, ReplContext, ReplDisplay, ReplMsg};
+use clap::{ArgMatches, Parser};
+use reedline_repl_rs::Result;
+
+#[derive(Debug, Parser)]
+pub struct FunctionsOpts;
+
+impl CmdExector for FunctionsOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        backend.functions(self).await?.display().await
+    }
+}
+
+pub fn functions(_args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let (msg, tx) = ReplMsg::new(FunctionsOpts);
+    let res = context.send(msg, tx);
+    Ok(res)
+}
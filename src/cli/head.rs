@@ -1,34 +1,114 @@
+use super::{dataset_name, parse_dataset_name, timed};
 use crate::{Backend, CmdExector, ReplContext, ReplDisplay, ReplMsg};
 use clap::{ArgMatches, Parser};
 use reedline_repl_rs::Result;
 
 #[derive(Debug, Parser)]
 pub struct HeadOpts {
-    #[arg(long, help = "Name of the dataset")]
+    #[arg(value_parser = parse_dataset_name, help = "Name of the dataset")]
     pub name: String,
-    #[arg(short, long, help = "Number of rows to show")]
+    #[arg(short, long, help = "Number of rows to show (default: 5)")]
     pub n: Option<usize>,
+    #[arg(
+        short,
+        long,
+        conflicts_with_all = ["sample", "sample_n"],
+        help = "Show the last rows instead of the first ones"
+    )]
+    pub tail: bool,
+    #[arg(
+        short,
+        long,
+        default_value_t = 0,
+        help = "Number of rows to skip, from the end with --tail"
+    )]
+    pub offset: usize,
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        help = "Columns to show, e.g. a,b,c"
+    )]
+    pub columns: Option<Vec<String>>,
+    #[arg(
+        short = 'w',
+        long = "where",
+        help = "Only show rows matching a sql expression, e.g. \"age > 30\""
+    )]
+    pub filter: Option<String>,
+    #[arg(
+        long,
+        value_parser = parse_fraction,
+        conflicts_with = "sample_n",
+        help = "Keep each row with the given probability, e.g. 0.01, all sampled rows are shown unless -n is given"
+    )]
+    pub sample: Option<f64>,
+    #[arg(long, help = "Show n random rows")]
+    pub sample_n: Option<usize>,
+    #[arg(
+        long,
+        help = "Seed of the random sample, the same seed gives the same rows"
+    )]
+    pub seed: Option<u64>,
 }
+
 pub fn head(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
-    let name = args
-        .get_one::<String>("name")
-        .expect("expect name")
-        .to_owned();
+    let name = dataset_name(&args);
     let n = args.get_one::<usize>("n").copied();
-    let opts = HeadOpts::new(name, n);
+    let tail = args.get_flag("tail");
+    let offset = args.get_one::<usize>("offset").copied().unwrap_or(0);
+    let columns = args
+        .get_many::<String>("columns")
+        .map(|v| v.cloned().collect());
+    let filter = args.get_one::<String>("filter").cloned();
+    let sample = args.get_one::<f64>("sample").copied();
+    let sample_n = args.get_one::<usize>("sample_n").copied();
+    let seed = args.get_one::<u64>("seed").copied();
+    let opts = HeadOpts::new(
+        name, n, tail, offset, columns, filter, sample, sample_n, seed,
+    );
     let (msg, tx) = ReplMsg::new(opts);
     let res = context.send(msg, tx);
     Ok(res)
 }
 
 impl HeadOpts {
-    pub fn new(name: String, n: Option<usize>) -> Self {
-        HeadOpts { name, n }
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        name: String,
+        n: Option<usize>,
+        tail: bool,
+        offset: usize,
+        columns: Option<Vec<String>>,
+        filter: Option<String>,
+        sample: Option<f64>,
+        sample_n: Option<usize>,
+        seed: Option<u64>,
+    ) -> Self {
+        HeadOpts {
+            name,
+            n,
+            tail,
+            offset,
+            columns,
+            filter,
+            sample,
+            sample_n,
+            seed,
+        }
+    }
+}
+
+fn parse_fraction(s: &str) -> std::result::Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(p) if (0.0..=1.0).contains(&p) => Ok(p),
+        _ => Err(format!("Invalid fraction: {}, expect 0-1", s)),
     }
 }
 
 impl CmdExector for HeadOpts {
     async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
-        backend.head(self).await?.display().await
+        let backend = &*backend;
+        timed(backend, async { backend.head(self).await?.display().await }).await
     }
 }
@@ -0,0 +1,81 @@
+use std::fmt::Display;
+
+use super::{dataset_name, parse_dataset_name};
+use crate::{Backend, CmdExector, ReplContext, ReplDisplay, ReplMsg};
+use clap::{ArgMatches, Parser, ValueEnum};
+use reedline_repl_rs::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DateBucket {
+    Day,
+    Week,
+    Month,
+}
+
+#[derive(Debug, Parser)]
+pub struct HistOpts {
+    #[arg(value_parser = parse_dataset_name, help = "Name of the dataset")]
+    pub name: String,
+    #[arg(help = "Column to show, strings and lists are bucketed by length")]
+    pub column: String,
+    #[arg(short, long, default_value_t = 20, help = "Number of bins")]
+    pub bins: usize,
+    #[arg(
+        short = 'u',
+        long,
+        value_enum,
+        help = "Bucket a temporal column by calendar day, week or month instead of equal width bins"
+    )]
+    pub unit: Option<DateBucket>,
+    #[arg(short, long, default_value_t = 50, help = "Max length of a bar")]
+    pub width: usize,
+}
+
+pub fn hist(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let name = dataset_name(&args);
+    let column = args
+        .get_one::<String>("column")
+        .expect("expect column")
+        .to_owned();
+    let bins = args.get_one::<usize>("bins").copied().unwrap_or(20);
+    let unit = args.get_one::<DateBucket>("unit").copied();
+    let width = args.get_one::<usize>("width").copied().unwrap_or(50);
+    let opts = HistOpts::new(name, column, bins, unit, width);
+    let (msg, tx) = ReplMsg::new(opts);
+    let res = context.send(msg, tx);
+    Ok(res)
+}
+
+impl HistOpts {
+    pub fn new(
+        name: String,
+        column: String,
+        bins: usize,
+        unit: Option<DateBucket>,
+        width: usize,
+    ) -> Self {
+        HistOpts {
+            name,
+            column,
+            bins,
+            unit,
+            width,
+        }
+    }
+}
+
+impl Display for DateBucket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DateBucket::Day => write!(f, "day"),
+            DateBucket::Week => write!(f, "week"),
+            DateBucket::Month => write!(f, "month"),
+        }
+    }
+}
+
+impl CmdExector for HistOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        backend.hist(self).await?.display().await
+    }
+}
@@ -3,16 +3,30 @@ use clap::{ArgMatches, Parser};
 use reedline_repl_rs::Result;
 
 #[derive(Debug, Parser)]
-pub struct ListOpts;
+pub struct ListOpts {
+    #[arg(
+        short,
+        long,
+        help = "Also show the source, format, estimated rows and registration time"
+    )]
+    pub verbose: bool,
+}
+
+impl ListOpts {
+    pub fn new(verbose: bool) -> Self {
+        ListOpts { verbose }
+    }
+}
 
-pub fn list(_args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
-    let (msg, tx) = ReplMsg::new(ListOpts);
+pub fn list(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let verbose = args.get_flag("verbose");
+    let (msg, tx) = ReplMsg::new(ListOpts::new(verbose));
     let res = context.send(msg, tx);
     Ok(res)
 }
 
 impl CmdExector for ListOpts {
     async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
-        backend.list().await?.display().await
+        backend.list(self).await?.display().await
     }
 }
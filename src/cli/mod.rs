@@ -1,19 +1,51 @@
-use std::process::exit;
+use std::{future::Future, process::exit};
 
-use clap::Parser;
+use clap::{ArgMatches, CommandFactory, Parser};
 use describe::DescribeOpts;
 use enum_dispatch::enum_dispatch;
 use head::HeadOpts;
+use hist::HistOpts;
+pub mod alias;
 pub mod connect;
+pub mod derive;
 pub mod describe;
+pub mod diff;
+pub mod drop;
+pub mod explain;
+pub mod functions;
 pub mod head;
+pub mod hist;
 pub mod list;
+pub mod params;
+pub mod parquet_meta;
+pub mod profile;
+pub mod refresh;
+pub mod rename;
 pub mod schema;
+pub mod set;
+pub mod show;
 pub mod sql;
+pub mod timing;
+pub mod udf;
+use alias::AliasOpts;
 use connect::ConnectOpts;
+use derive::{DeriveOpts, MaterializeOpts};
+use diff::DiffOpts;
+use drop::DropOpts;
+use explain::ExplainOpts;
+use functions::FunctionsOpts;
 use list::ListOpts;
+use params::{ExecuteOpts, LetOpts, PrepareOpts};
+use parquet_meta::ParquetMetaOpts;
+use profile::ProfileOpts;
+use refresh::RefreshOpts;
+use rename::RenameOpts;
 use schema::SchemaOpts;
+use set::SetOpts;
+use show::ShowOpts;
 use sql::SqlOpts;
+use timing::TimingOpts;
+use udf::UdfOpts;
 
 use crate::{Backend, CmdExector};
 
@@ -27,14 +59,100 @@ pub enum ReplCommand {
     Connect(ConnectOpts),
     #[command(name = "list", about = "List all registered datasets")]
     List(ListOpts),
+    #[command(
+        name = "drop",
+        about = "Remove a registered dataset, unless a derived dataset uses it"
+    )]
+    Drop(DropOpts),
+    #[command(
+        name = "rename",
+        about = "Rename a registered dataset, unless a derived dataset uses it"
+    )]
+    Rename(RenameOpts),
+    #[command(
+        name = "alias",
+        about = "Give a dataset another name, as a view over it"
+    )]
+    Alias(AliasOpts),
+    #[command(
+        name = "refresh",
+        about = "Register a dataset again to pick up new files or a changed schema"
+    )]
+    Refresh(RefreshOpts),
+    #[command(
+        name = "derive",
+        about = "Register a view over a query, e.g. derive clean_users as \"select ...\""
+    )]
+    Derive(DeriveOpts),
+    #[command(
+        name = "materialize",
+        about = "Run a query and keep its result in memory as a dataset"
+    )]
+    Materialize(MaterializeOpts),
     #[command(name = "schema", about = "describe a dataset schema")]
     Schema(SchemaOpts),
     #[command(name = "describe", about = "show the first n rows of a dataset")]
     Describe(DescribeOpts),
     #[command(name = "head", about = "show the first n rows of a dataset")]
     Head(HeadOpts),
+    #[command(name = "hist", about = "show a histogram of a column")]
+    Hist(HistOpts),
+    #[command(
+        name = "profile",
+        about = "profile a dataset and write an html or json report"
+    )]
+    Profile(ProfileOpts),
+    #[command(
+        name = "diff",
+        about = "compare two datasets by key: schema, added, removed and changed rows"
+    )]
+    Diff(DiffOpts),
+    #[command(
+        name = "parquet-meta",
+        about = "inspect row groups and column chunks of parquet files"
+    )]
+    ParquetMeta(ParquetMetaOpts),
     #[command(name = "sql", about = "query a dataset with sql")]
     Sql(SqlOpts),
+    #[command(
+        name = "let",
+        about = "Set a session variable used as $name in queries, e.g. let min_age = 30"
+    )]
+    Let(LetOpts),
+    #[command(
+        name = "prepare",
+        about = "Prepare a query with parameters, e.g. prepare q as \"select ... $1\""
+    )]
+    Prepare(PrepareOpts),
+    #[command(name = "execute", about = "Run a prepared query, e.g. execute q 30")]
+    Execute(ExecuteOpts),
+    #[command(
+        name = "explain",
+        about = "show the logical, optimized and physical plans of a query"
+    )]
+    Explain(ExplainOpts),
+    #[command(
+        name = "functions",
+        about = "list the functions registered by plugins, usable in sql"
+    )]
+    Functions(FunctionsOpts),
+    #[command(
+        name = "udf",
+        about = "load sql functions compiled to WebAssembly, e.g. udf load assets/udf.wasm"
+    )]
+    Udf(UdfOpts),
+    #[command(
+        name = "timing",
+        about = "show rows, time, bytes scanned and peak memory after sql, head and describe"
+    )]
+    Timing(TimingOpts),
+    #[command(
+        name = "set",
+        about = "change a session setting, e.g. set memory_limit 2GB"
+    )]
+    Set(SetOpts),
+    #[command(name = "show", about = "show session settings with their values")]
+    Show(ShowOpts),
     #[command(name = "exit", about = "exit the repl")]
     Exit(ExitOpts),
 }
@@ -47,3 +165,229 @@ impl CmdExector for ExitOpts {
         exit(0);
     }
 }
+
+/// Keywords that start a bare SQL statement, routed straight to `SqlOpts`; the taotie
+/// commands of the same name are reached with the dot, e.g. `.describe`
+const SQL_KEYWORDS: &[&str] = &[
+    "select", "with", "create", "insert", "explain", "show", "describe",
+];
+
+/// One line of repl input, classified like the sqlite3/duckdb shells:
+/// `.connect ...` is a `ReplCommand`, `select ...` is bare SQL
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReplInput {
+    Empty,
+    Sql(String),
+    /// command name (without the leading dot) followed by its arguments
+    Command(Vec<String>),
+}
+
+impl ReplInput {
+    pub fn parse(line: &str) -> Self {
+        let line = line.trim();
+        if line.is_empty() {
+            return ReplInput::Empty;
+        }
+        if let Some(cmd) = line.strip_prefix('.') {
+            return ReplInput::Command(split_args(cmd));
+        }
+        let first = line
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        if SQL_KEYWORDS.contains(&first.as_str()) {
+            let query = line.trim_end_matches(';').trim_end().to_string();
+            ReplInput::Sql(query)
+        } else {
+            // keep accepting the old style without the dot, e.g. `connect ...`
+            ReplInput::Command(split_args(line))
+        }
+    }
+}
+
+/// output of a command followed by the timing footer when `timing on`
+pub(crate) async fn timed<T: Backend>(
+    backend: &T,
+    run: impl Future<Output = anyhow::Result<String>>,
+) -> anyhow::Result<String> {
+    let start = backend.timing_start();
+    let mut out = run.await?;
+    if let Some(footer) = backend.timing_footer(start) {
+        out.push_str(&format!("\n{}", footer));
+    }
+    Ok(out)
+}
+
+/// the positional dataset name shared by every command working on a dataset
+pub(crate) fn dataset_name(args: &ArgMatches) -> String {
+    args.get_one::<String>("name")
+        .expect("expect name")
+        .to_owned()
+}
+
+/// value parser of dataset names, whether it is registered is checked by the backend
+pub(crate) fn parse_dataset_name(s: &str) -> Result<String, String> {
+    match s.trim() {
+        "" => Err("Dataset name can't be empty".to_string()),
+        name => Ok(name.to_string()),
+    }
+}
+
+/// dot-command names for completion and highlighting, e.g. `.connect`
+pub fn dot_commands() -> Vec<String> {
+    ReplCommand::command()
+        .get_subcommands()
+        .map(|cmd| format!(".{}", cmd.get_name()))
+        .chain(std::iter::once(".help".to_string()))
+        .collect()
+}
+
+/// sql keywords for completion and highlighting, both lower and upper case
+pub fn sql_keywords() -> Vec<String> {
+    SQL_KEYWORDS
+        .iter()
+        .flat_map(|k| [k.to_string(), k.to_uppercase()])
+        .collect()
+}
+
+/// split a command line on whitespace, a double quoted string is one argument,
+/// `\"` inside it keeps the quote, e.g. for a quoted identifier in a query
+fn split_args(line: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut cur = String::new();
+    let mut quoted = false;
+    let mut has_arg = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if quoted && chars.peek() == Some(&'"') => {
+                cur.push(chars.next().unwrap_or('"'));
+            }
+            '"' => {
+                quoted = !quoted;
+                has_arg = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if has_arg {
+                    args.push(std::mem::take(&mut cur));
+                    has_arg = false;
+                }
+            }
+            c => {
+                cur.push(c);
+                has_arg = true;
+            }
+        }
+    }
+    if has_arg {
+        args.push(cur);
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(args: &[&str]) -> ReplInput {
+        ReplInput::Command(args.iter().map(|s| s.to_string()).collect())
+    }
+
+    #[test]
+    fn parse_should_classify_lines() {
+        assert_eq!(ReplInput::parse("   "), ReplInput::Empty);
+        assert_eq!(
+            ReplInput::parse("SELECT * FROM t;"),
+            ReplInput::Sql("SELECT * FROM t".to_string())
+        );
+        assert_eq!(
+            ReplInput::parse("with a as (select 1) select * from a"),
+            ReplInput::Sql("with a as (select 1) select * from a".to_string())
+        );
+        assert_eq!(
+            ReplInput::parse(".head t -n 3"),
+            command(&["head", "t", "-n", "3"])
+        );
+    }
+
+    #[test]
+    fn parse_should_send_sql_keywords_to_sql() {
+        assert_eq!(
+            ReplInput::parse("describe test2"),
+            ReplInput::Sql("describe test2".to_string())
+        );
+        assert_eq!(
+            ReplInput::parse("SHOW ALL;"),
+            ReplInput::Sql("SHOW ALL".to_string())
+        );
+        assert_eq!(
+            ReplInput::parse("explain select 1"),
+            ReplInput::Sql("explain select 1".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_should_keep_taotie_commands_behind_the_dot() {
+        assert_eq!(
+            ReplInput::parse(".describe test2"),
+            command(&["describe", "test2"])
+        );
+        assert_eq!(
+            ReplInput::parse(".show max_rows"),
+            command(&["show", "max_rows"])
+        );
+        assert_eq!(
+            ReplInput::parse(".explain \"select 1\""),
+            command(&["explain", "select 1"])
+        );
+        // other commands still work without the dot
+        assert_eq!(ReplInput::parse("list"), command(&["list"]));
+    }
+
+    #[test]
+    fn split_args_should_handle_quotes() {
+        assert_eq!(
+            split_args(r#"connect "assets/my data.csv"  -n "My Data""#),
+            vec!["connect", "assets/my data.csv", "-n", "My Data"]
+        );
+        assert_eq!(
+            split_args(r#"sql "select \"kit number\" from j""#),
+            vec!["sql", r#"select "kit number" from j"#]
+        );
+        assert_eq!(split_args(r#"set key """#), vec!["set", "key", ""]);
+        assert!(split_args("  ").is_empty());
+    }
+
+    #[test]
+    fn udf_should_parse_types() {
+        let parse = |line: &str| {
+            ReplCommand::try_parse_from(
+                std::iter::once("taotie".to_string()).chain(split_args(line)),
+            )
+        };
+        match parse("udf load mask.wasm --name mask_email -a utf8,i64 --returns string") {
+            Ok(ReplCommand::Udf(opts)) => {
+                assert_eq!(opts.name.as_deref(), Some("mask_email"));
+                assert_eq!(opts.args, [udf::WasmType::Utf8, udf::WasmType::Int64]);
+                assert_eq!(opts.returns, Some(udf::WasmType::Utf8));
+            }
+            other => panic!("expect udf, got {:?}", other),
+        }
+        // types are declared for a single named function only
+        assert!(parse("udf load mask.wasm --args utf8").is_err());
+        assert!(parse("udf load mask.wasm --name mask_email").is_err());
+    }
+
+    #[test]
+    fn connect_should_keep_dataset_names() {
+        for name in ["order", "user", "My Data", "用户"] {
+            let line = format!(r#"connect assets/juventus.csv -n "{}""#, name);
+            let args = std::iter::once("taotie".to_string()).chain(split_args(&line));
+            match ReplCommand::try_parse_from(args) {
+                Ok(ReplCommand::Connect(opts)) => assert_eq!(opts.dataset_name(), name),
+                other => panic!("expect connect, got {:?}", other),
+            }
+        }
+    }
+}
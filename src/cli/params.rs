@@ -0,0 +1,157 @@
+use super::timed;
+use crate::{Backend, CmdExector, ReplContext, ReplDisplay, ReplMsg};
+use clap::{ArgMatches, Parser};
+use reedline_repl_rs::Result;
+
+#[derive(Debug, Parser)]
+pub struct LetOpts {
+    #[arg(
+        value_parser = parse_variable_name,
+        requires_all = ["keyword", "value"],
+        help = "Variable name, used as $name in queries, lists the variables when omitted"
+    )]
+    pub name: Option<String>,
+    #[arg(value_parser = ["="], value_name = "=", hide = true)]
+    pub keyword: Option<String>,
+    #[arg(
+        num_args = 1..,
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        help = "SQL expression of the value, e.g. 30, 'Turin' or date '2024-01-01'"
+    )]
+    pub value: Vec<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct PrepareOpts {
+    #[arg(value_parser = parse_variable_name, help = "Name of the prepared statement")]
+    pub name: String,
+    #[arg(value_parser = ["as"], value_name = "as", hide = true)]
+    pub keyword: String,
+    #[arg(help = "SQL query with parameters $1, $2, ...")]
+    pub query: String,
+}
+
+#[derive(Debug, Parser)]
+pub struct ExecuteOpts {
+    #[arg(help = "Name of the prepared statement")]
+    pub name: String,
+    #[arg(
+        num_args = 0..,
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        help = "Values of $1, $2, ..., each a SQL expression"
+    )]
+    pub params: Vec<String>,
+}
+
+impl LetOpts {
+    pub fn new(name: Option<String>, value: Vec<String>) -> Self {
+        LetOpts {
+            keyword: name.as_ref().map(|_| "=".into()),
+            name,
+            value,
+        }
+    }
+
+    /// the value expression, split on whitespace by the repl
+    pub fn expr(&self) -> String {
+        self.value.join(" ")
+    }
+}
+
+impl PrepareOpts {
+    pub fn new(name: String, query: String) -> Self {
+        PrepareOpts {
+            name,
+            keyword: "as".into(),
+            query,
+        }
+    }
+}
+
+impl ExecuteOpts {
+    pub fn new(name: String, params: Vec<String>) -> Self {
+        ExecuteOpts { name, params }
+    }
+}
+
+impl CmdExector for LetOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        match self.name {
+            Some(_) => backend.define(self).await,
+            None => backend.variables().await?.display().await,
+        }
+    }
+}
+
+impl CmdExector for PrepareOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        backend.prepare(self).await
+    }
+}
+
+impl CmdExector for ExecuteOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        let backend = &*backend;
+        timed(backend, async {
+            backend.execute(self).await?.display().await
+        })
+        .await
+    }
+}
+
+pub fn define(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let name = args.get_one::<String>("name").map(|s| s.to_owned());
+    let value = strings(&args, "value");
+    let (msg, tx) = ReplMsg::new(LetOpts::new(name, value));
+    let res = context.send(msg, tx);
+    Ok(res)
+}
+
+pub fn prepare(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let name = args
+        .get_one::<String>("name")
+        .expect("expect name")
+        .to_owned();
+    let query = args
+        .get_one::<String>("query")
+        .expect("expect query")
+        .trim()
+        .trim_end_matches(';')
+        .to_string();
+    let (msg, tx) = ReplMsg::new(PrepareOpts::new(name, query));
+    let res = context.send(msg, tx);
+    Ok(res)
+}
+
+pub fn execute(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let name = args
+        .get_one::<String>("name")
+        .expect("expect name")
+        .to_owned();
+    let params = strings(&args, "params");
+    let (msg, tx) = ReplMsg::new(ExecuteOpts::new(name, params));
+    let res = context.send(msg, tx);
+    Ok(res)
+}
+
+fn strings(args: &ArgMatches, id: &str) -> Vec<String> {
+    args.get_many::<String>(id)
+        .map(|values| values.cloned().collect())
+        .unwrap_or_default()
+}
+
+/// `$1`, `$2` ... are the parameters of prepared statements, so names can't start with a digit
+fn parse_variable_name(s: &str) -> std::result::Result<String, String> {
+    let name = s.trim().trim_start_matches('$');
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => {}
+        _ => return Err("Name must start with a letter or _".to_string()),
+    }
+    if !chars.all(|c| c.is_alphanumeric() || c == '_') {
+        return Err("Name can only contain letters, digits and _".to_string());
+    }
+    Ok(name.to_string())
+}
@@ -0,0 +1,34 @@
+use clap::{ArgMatches, Parser};
+
+use crate::{Backend, CmdExector, ReplContext, ReplDisplay, ReplMsg};
+use reedline_repl_rs::Result;
+
+#[derive(Debug, Parser)]
+pub struct ParquetMetaOpts {
+    #[arg(help = "Name of a parquet dataset, or path to a parquet file or directory")]
+    pub target: String,
+}
+
+impl ParquetMetaOpts {
+    pub fn new(target: String) -> Self {
+        ParquetMetaOpts { target }
+    }
+}
+
+impl CmdExector for ParquetMetaOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        let meta = backend.parquet_meta(self).await?;
+        meta.display().await
+    }
+}
+
+pub fn parquet_meta(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let target = args
+        .get_one::<String>("target")
+        .expect("expect target")
+        .to_owned();
+    let opts = ParquetMetaOpts::new(target);
+    let (msg, tx) = ReplMsg::new(opts);
+    let res = context.send(msg, tx);
+    Ok(res)
+}
@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+
+use super::{dataset_name, parse_dataset_name};
+use crate::{Backend, CmdExector, ReplContext, ReplDisplay, ReplMsg};
+use clap::{ArgMatches, Parser};
+use reedline_repl_rs::Result;
+
+#[derive(Debug, Clone)]
+pub enum ProfileOutput {
+    Html(PathBuf),
+    Json(PathBuf),
+}
+
+#[derive(Debug, Parser)]
+pub struct ProfileOpts {
+    #[arg(value_parser = parse_dataset_name, help = "Name of the dataset")]
+    pub name: String,
+    #[arg(short, long, value_parser = verify_out_path, help = "Report file, .html or .json")]
+    pub out: ProfileOutput,
+    #[arg(short, long, default_value_t = 20, help = "Number of histogram bins")]
+    pub bins: usize,
+    #[arg(
+        short = 'k',
+        long,
+        default_value_t = 10,
+        help = "Number of most frequent values and patterns per column"
+    )]
+    pub top_k: usize,
+}
+
+pub fn profile(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let name = dataset_name(&args);
+    let out = args
+        .get_one::<ProfileOutput>("out")
+        .expect("expect out")
+        .to_owned();
+    let bins = args.get_one::<usize>("bins").copied().unwrap_or(20);
+    let top_k = args.get_one::<usize>("top_k").copied().unwrap_or(10);
+    let opts = ProfileOpts::new(name, out, bins, top_k);
+    let (msg, tx) = ReplMsg::new(opts);
+    let res = context.send(msg, tx);
+    Ok(res)
+}
+
+impl ProfileOpts {
+    pub fn new(name: String, out: ProfileOutput, bins: usize, top_k: usize) -> Self {
+        ProfileOpts {
+            name,
+            out,
+            bins,
+            top_k,
+        }
+    }
+}
+
+impl ProfileOutput {
+    pub fn path(&self) -> &PathBuf {
+        match self {
+            ProfileOutput::Html(path) | ProfileOutput::Json(path) => path,
+        }
+    }
+}
+
+impl CmdExector for ProfileOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        let summary = backend.profile(self).await?.display().await?;
+        Ok(format!(
+            "{}\nprofile written to {}",
+            summary,
+            self.out.path().display()
+        ))
+    }
+}
+
+fn verify_out_path(s: &str) -> std::result::Result<ProfileOutput, String> {
+    if s.ends_with(".html") || s.ends_with(".htm") {
+        Ok(ProfileOutput::Html(PathBuf::from(s)))
+    } else if s.ends_with(".json") {
+        Ok(ProfileOutput::Json(PathBuf::from(s)))
+    } else {
+        Err("Invalid report file, expect .html or .json".to_string())
+    }
+}
@@ -0,0 +1,30 @@
+use super::{dataset_name, parse_dataset_name};
+use crate::{Backend, CmdExector, ReplContext, ReplMsg};
+use clap::{ArgMatches, Parser};
+use reedline_repl_rs::Result;
+
+#[derive(Debug, Parser)]
+pub struct RefreshOpts {
+    #[arg(value_parser = parse_dataset_name, help = "Name of the dataset")]
+    pub name: String,
+}
+
+impl RefreshOpts {
+    pub fn new(name: String) -> Self {
+        RefreshOpts { name }
+    }
+}
+
+impl CmdExector for RefreshOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        backend.refresh(self).await?;
+        Ok(format!("refreshed {}", self.name))
+    }
+}
+
+pub fn refresh(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let name = dataset_name(&args);
+    let (msg, tx) = ReplMsg::new(RefreshOpts::new(name));
+    let res = context.send(msg, tx);
+    Ok(res)
+}
@@ -0,0 +1,36 @@
+use super::{dataset_name, parse_dataset_name};
+use crate::{Backend, CmdExector, ReplContext, ReplMsg};
+use clap::{ArgMatches, Parser};
+use reedline_repl_rs::Result;
+
+#[derive(Debug, Parser)]
+pub struct RenameOpts {
+    #[arg(value_parser = parse_dataset_name, help = "Name of the dataset")]
+    pub name: String,
+    #[arg(value_parser = parse_dataset_name, help = "New name of the dataset")]
+    pub new_name: String,
+}
+
+impl RenameOpts {
+    pub fn new(name: String, new_name: String) -> Self {
+        RenameOpts { name, new_name }
+    }
+}
+
+impl CmdExector for RenameOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        backend.rename(self).await?;
+        Ok(format!("renamed {} to {}", self.name, self.new_name))
+    }
+}
+
+pub fn rename(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let name = dataset_name(&args);
+    let new_name = args
+        .get_one::<String>("new_name")
+        .expect("expect new_name")
+        .to_owned();
+    let (msg, tx) = ReplMsg::new(RenameOpts::new(name, new_name));
+    let res = context.send(msg, tx);
+    Ok(res)
+}
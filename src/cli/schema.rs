@@ -1,17 +1,32 @@
 use clap::{ArgMatches, Parser};
 
+use super::{dataset_name, parse_dataset_name};
 use crate::{Backend, CmdExector, ReplContext, ReplDisplay, ReplMsg};
 use reedline_repl_rs::Result;
 
 #[derive(Debug, Parser)]
 pub struct SchemaOpts {
-    #[arg(help = "Name of the dataset")]
+    #[arg(value_parser = parse_dataset_name, help = "Name of the dataset")]
     pub name: String,
+    #[arg(short, long, help = "Show nested columns as a tree")]
+    pub tree: bool,
+    #[arg(
+        short,
+        long,
+        num_args = 0..=1,
+        default_missing_value = "3",
+        help = "Expand struct columns into dotted leaf columns, up to the given depth (default: 3)"
+    )]
+    pub flatten: Option<usize>,
 }
 
 impl SchemaOpts {
-    pub fn new(name: String) -> Self {
-        SchemaOpts { name }
+    pub fn new(name: String, tree: bool, flatten: Option<usize>) -> Self {
+        SchemaOpts {
+            name,
+            tree,
+            flatten,
+        }
     }
 }
 
@@ -23,11 +38,10 @@ impl CmdExector for SchemaOpts {
 }
 
 pub fn schema(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
-    let name = args
-        .get_one::<String>("name")
-        .expect("expect name")
-        .to_owned();
-    let opts = SchemaOpts::new(name);
+    let name = dataset_name(&args);
+    let tree = args.get_flag("tree");
+    let flatten = args.get_one::<usize>("flatten").copied();
+    let opts = SchemaOpts::new(name, tree, flatten);
     let (msg, tx) = ReplMsg::new(opts);
     let res = context.send(msg, tx);
     Ok(res)
@@ -0,0 +1,161 @@
+use std::fmt;
+
+use crate::{Backend, CmdExector, ReplContext, ReplMsg};
+use clap::{ArgMatches, Parser, ValueEnum};
+use datafusion::config::ConfigOptions;
+use reedline_repl_rs::Result;
+
+/// taotie's own settings and what they do, the datafusion ones are described by datafusion
+pub const TAOTIE_SETTINGS: &[(&str, &str)] = &[
+    (
+        "format",
+        "Output format of sql and head: table, csv or json",
+    ),
+    (
+        "max_rows",
+        "Rows shown by sql and head, a number or unlimited",
+    ),
+    (
+        "timing",
+        "Show the timing footer after sql, head and describe: on or off",
+    ),
+    (
+        "memory_limit",
+        "Memory available to queries, e.g. 2GB, or unlimited",
+    ),
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Parser)]
+pub struct SetOpts {
+    #[arg(help = "Setting to change, e.g. max_rows or target_partitions, see `.show`")]
+    pub key: String,
+    #[arg(help = "New value")]
+    pub value: String,
+}
+
+impl SetOpts {
+    pub fn new(key: String, value: String) -> Self {
+        SetOpts { key, value }
+    }
+}
+
+impl CmdExector for SetOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        backend.set(self).await
+    }
+}
+
+pub fn set(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let key = args
+        .get_one::<String>("key")
+        .expect("expect key")
+        .to_owned();
+    let value = args
+        .get_one::<String>("value")
+        .expect("expect value")
+        .to_owned();
+    let (msg, tx) = ReplMsg::new(SetOpts::new(key, value));
+    let res = context.send(msg, tx);
+    Ok(res)
+}
+
+/// a size like `512MB`, `1.5GiB` or `1048576`, `None` for `unlimited`
+pub fn parse_size(s: &str) -> anyhow::Result<Option<usize>> {
+    let s = s.trim().to_lowercase();
+    if matches!(s.as_str(), "unlimited" | "none" | "off" | "0") {
+        return Ok(None);
+    }
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let num: f64 = num
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid size '{}', e.g. 512MB or 2GB", s))?;
+    let unit: f64 = match unit.trim() {
+        "" | "b" => 1.0,
+        "k" | "kb" | "kib" => 1024.0,
+        "m" | "mb" | "mib" => 1024.0 * 1024.0,
+        "g" | "gb" | "gib" => 1024.0 * 1024.0 * 1024.0,
+        unit => anyhow::bail!("unknown size unit '{}', use KB, MB or GB", unit),
+    };
+    Ok(Some((num * unit) as usize))
+}
+
+/// `on`/`off`, `true`/`false` or `1`/`0`
+pub fn parse_switch(s: &str) -> anyhow::Result<bool> {
+    match s.trim().to_lowercase().as_str() {
+        "on" | "true" | "1" => Ok(true),
+        "off" | "false" | "0" => Ok(false),
+        _ => anyhow::bail!("invalid value '{}', use on or off", s),
+    }
+}
+
+/// every setting key, used for tab completion: taotie's, the datafusion ones both in full
+/// (`datafusion.execution.batch_size`) and without the namespace (`batch_size`)
+pub fn setting_keys() -> Vec<String> {
+    let mut keys: Vec<String> = TAOTIE_SETTINGS.iter().map(|(k, _)| k.to_string()).collect();
+    for entry in ConfigOptions::new().entries() {
+        if let Some((_, short)) = entry
+            .key
+            .split_once('.')
+            .and_then(|(_, k)| k.split_once('.'))
+        {
+            keys.push(short.to_string());
+        }
+        keys.push(entry.key);
+    }
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OutputFormat::Table => "table",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Json => "json",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_size_should_read_units() {
+        assert_eq!(parse_size("1048576").unwrap(), Some(1024 * 1024));
+        assert_eq!(parse_size("512MB").unwrap(), Some(512 * 1024 * 1024));
+        assert_eq!(
+            parse_size(" 1.5 GiB ").unwrap(),
+            Some(3 * 512 * 1024 * 1024)
+        );
+        assert_eq!(parse_size("2k").unwrap(), Some(2048));
+        assert_eq!(parse_size("unlimited").unwrap(), None);
+        assert_eq!(parse_size("0").unwrap(), None);
+        assert!(parse_size("2TB").is_err());
+        assert!(parse_size("lots").is_err());
+    }
+
+    #[test]
+    fn parse_switch_should_accept_on_and_off() {
+        for on in ["on", "ON", "true", "1"] {
+            assert!(parse_switch(on).unwrap());
+        }
+        for off in ["off", "False", "0"] {
+            assert!(!parse_switch(off).unwrap());
+        }
+        assert!(parse_switch("maybe").is_err());
+    }
+}
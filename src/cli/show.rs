@@ -0,0 +1,28 @@
+use crate::{Backend, CmdExector, ReplContext, ReplDisplay, ReplMsg};
+use clap::{ArgMatches, Parser};
+use reedline_repl_rs::Result;
+
+#[derive(Debug, Parser)]
+pub struct ShowOpts {
+    #[arg(help = "Setting to show, all of them when omitted")]
+    pub key: Option<String>,
+}
+
+impl ShowOpts {
+    pub fn new(key: Option<String>) -> Self {
+        ShowOpts { key }
+    }
+}
+
+impl CmdExector for ShowOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        backend.show(self).await?.display().await
+    }
+}
+
+pub fn show(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let key = args.get_one::<String>("key").map(|s| s.to_owned());
+    let (msg, tx) = ReplMsg::new(ShowOpts::new(key));
+    let res = context.send(msg, tx);
+    Ok(res)
+}
@@ -1,32 +1,67 @@
+use std::path::PathBuf;
+
+use super::timed;
 use crate::{Backend, CmdExector, ReplContext, ReplDisplay, ReplMsg};
+use anyhow::Context;
 use clap::{ArgMatches, Parser};
 use reedline_repl_rs::Result;
 
 #[derive(Debug, Parser)]
 pub struct SqlOpts {
-    #[arg(help = "SQL query")]
-    pub query: String,
+    #[arg(
+        help = "SQL query, statements separated by `;` run in order",
+        required_unless_present = "file"
+    )]
+    pub query: Option<String>,
+    #[arg(
+        short,
+        long,
+        conflicts_with = "query",
+        help = "Run the statements of a sql script"
+    )]
+    pub file: Option<PathBuf>,
+    #[arg(
+        short,
+        long,
+        help = "Show the result of every statement, not only the last one"
+    )]
+    pub all: bool,
 }
 
 pub fn sql(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
-    let query = args
-        .get_one::<String>("query")
-        .expect("expect query")
-        .to_owned();
-    let opts = SqlOpts::new(query);
+    let query = args.get_one::<String>("query").map(|s| s.to_owned());
+    let file = args.get_one::<PathBuf>("file").map(|p| p.to_owned());
+    let all = args.get_flag("all");
+    let (msg, tx) = ReplMsg::new(SqlOpts::new(query, file, all));
+    Ok(context.send(msg, tx))
+}
+
+/// run a bare sql statement typed without the `sql "..."` wrapper
+pub fn run_sql(query: String, context: &ReplContext) -> Option<String> {
+    let opts = SqlOpts::new(Some(query), None, false);
     let (msg, tx) = ReplMsg::new(opts);
-    let res = context.send(msg, tx);
-    Ok(res)
+    context.send(msg, tx)
 }
 
 impl SqlOpts {
-    pub fn new(query: String) -> Self {
-        SqlOpts { query }
+    pub fn new(query: Option<String>, file: Option<PathBuf>, all: bool) -> Self {
+        SqlOpts { query, file, all }
+    }
+
+    /// the query, or the content of the script file
+    pub fn text(&self) -> anyhow::Result<String> {
+        match (&self.query, &self.file) {
+            (Some(query), _) => Ok(query.clone()),
+            (None, Some(file)) => std::fs::read_to_string(file)
+                .with_context(|| format!("can't read {}", file.display())),
+            (None, None) => anyhow::bail!("a query or --file is required"),
+        }
     }
 }
 
 impl CmdExector for SqlOpts {
     async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
-        backend.sql(self).await?.display().await
+        let backend = &*backend;
+        timed(backend, async { backend.sql(self).await?.display().await }).await
     }
 }
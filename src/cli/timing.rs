@@ -0,0 +1,35 @@
+use crate::{Backend, CmdExector, ReplContext, ReplMsg};
+use clap::{ArgMatches, Parser, ValueEnum};
+use reedline_repl_rs::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Switch {
+    On,
+    Off,
+}
+
+#[derive(Debug, Parser)]
+pub struct TimingOpts {
+    #[arg(value_enum, help = "on or off, toggles when omitted")]
+    pub switch: Option<Switch>,
+}
+
+impl TimingOpts {
+    pub fn new(switch: Option<Switch>) -> Self {
+        TimingOpts { switch }
+    }
+}
+
+impl CmdExector for TimingOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        let on = backend.timing(self.switch.map(|s| s == Switch::On));
+        Ok(format!("Timing is {}.", if on { "on" } else { "off" }))
+    }
+}
+
+pub fn timing(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let switch = args.get_one::<Switch>("switch").copied();
+    let (msg, tx) = ReplMsg::new(TimingOpts::new(switch));
+    let res = context.send(msg, tx);
+    Ok(res)
+}
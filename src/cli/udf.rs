@@ -0,0 +1,101 @@
+use crate::{Backend, CmdExector, ReplContext, ReplMsg};
+use clap::{ArgMatches, Parser, ValueEnum};
+use reedline_repl_rs::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum UdfAction {
+    Load,
+}
+
+/// sql types of the arguments and result of a wasm function
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum WasmType {
+    #[value(alias = "i32")]
+    Int32,
+    #[value(alias = "i64")]
+    Int64,
+    #[value(alias = "f32")]
+    Float32,
+    #[value(alias = "f64")]
+    Float64,
+    /// a (ptr, len) pair of i32 as an argument, an i64 of `ptr << 32 | len` as the result
+    #[value(alias = "string")]
+    Utf8,
+}
+
+#[derive(Debug, Parser)]
+pub struct UdfOpts {
+    #[arg(value_enum, help = "load registers the functions of a .wasm module")]
+    pub action: UdfAction,
+    #[arg(help = "Path of the .wasm module")]
+    pub path: String,
+    #[arg(
+        short,
+        long,
+        requires = "returns",
+        help = "SQL name of a single function, every numeric export is loaded when omitted"
+    )]
+    pub name: Option<String>,
+    #[arg(
+        short,
+        long,
+        requires = "name",
+        help = "Exported function to run, defaults to the name"
+    )]
+    pub export: Option<String>,
+    #[arg(
+        short,
+        long,
+        value_enum,
+        value_delimiter = ',',
+        requires = "name",
+        help = "Argument types, e.g. utf8,i64"
+    )]
+    pub args: Vec<WasmType>,
+    #[arg(short, long, value_enum, requires = "name", help = "Result type")]
+    pub returns: Option<WasmType>,
+}
+
+impl UdfOpts {
+    pub fn new(
+        action: UdfAction,
+        path: String,
+        name: Option<String>,
+        export: Option<String>,
+        args: Vec<WasmType>,
+        returns: Option<WasmType>,
+    ) -> Self {
+        UdfOpts {
+            action,
+            path,
+            name,
+            export,
+            args,
+            returns,
+        }
+    }
+}
+
+impl CmdExector for UdfOpts {
+    async fn execute<T: Backend>(&self, backend: &mut T) -> anyhow::Result<String> {
+        backend.udf(self).await
+    }
+}
+
+pub fn udf(args: ArgMatches, context: &mut ReplContext) -> Result<Option<String>> {
+    let action = *args.get_one::<UdfAction>("action").expect("expect action");
+    let path = args
+        .get_one::<String>("path")
+        .expect("expect path")
+        .to_owned();
+    let name = args.get_one::<String>("name").cloned();
+    let export = args.get_one::<String>("export").cloned();
+    let types = args
+        .get_many::<WasmType>("args")
+        .map(|types| types.copied().collect())
+        .unwrap_or_default();
+    let returns = args.get_one::<WasmType>("returns").copied();
+    let (msg, tx) = ReplMsg::new(UdfOpts::new(action, path, name, export, types, returns));
+    let res = context.send(msg, tx);
+    Ok(res)
+}
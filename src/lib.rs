@@ -1,12 +1,34 @@
-use std::{process::exit, thread};
+use std::{process::exit, thread, time::Instant};
 
-use backend::fusion::DataFusionBackend;
+use backend::fusion::{memory::RuntimeSettings, plugin::UdfPlugin, DataFusionBackend};
 use clap::ArgMatches;
 use cli::{
-    connect::ConnectOpts, describe::DescribeOpts, head::HeadOpts, list::ListOpts,
-    schema::SchemaOpts, sql::SqlOpts, ExitOpts, ReplCommand,
+    alias::AliasOpts,
+    connect::ConnectOpts,
+    derive::{DeriveOpts, MaterializeOpts},
+    describe::DescribeOpts,
+    diff::DiffOpts,
+    drop::DropOpts,
+    explain::ExplainOpts,
+    functions::FunctionsOpts,
+    head::HeadOpts,
+    hist::HistOpts,
+    list::ListOpts,
+    params::{ExecuteOpts, LetOpts, PrepareOpts},
+    parquet_meta::ParquetMetaOpts,
+    profile::ProfileOpts,
+    refresh::RefreshOpts,
+    rename::RenameOpts,
+    schema::SchemaOpts,
+    set::SetOpts,
+    show::ShowOpts,
+    sql::SqlOpts,
+    timing::TimingOpts,
+    udf::UdfOpts,
+    ExitOpts, ReplCommand,
 };
 use crossbeam_channel as mpsc;
+use datafusion::error::DataFusionError;
 use enum_dispatch::enum_dispatch;
 use reedline_repl_rs::CallBackMap;
 use tokio::runtime::Runtime;
@@ -24,13 +46,37 @@ trait ReplDisplay {
 
 trait Backend {
     // type DataFrame: ReplDisplay;
+    /// turn the timing footer on or off, toggle it without a value
+    fn timing(&mut self, on: Option<bool>) -> bool;
+    fn timing_start(&self) -> Instant;
+    fn timing_footer(&self, start: Instant) -> Option<String>;
+    async fn set(&mut self, opts: &SetOpts) -> anyhow::Result<String>;
+    async fn show(&self, opts: &ShowOpts) -> anyhow::Result<impl ReplDisplay>;
     async fn connect(&mut self, opts: &ConnectOpts) -> anyhow::Result<()>;
-    async fn list(&self) -> anyhow::Result<impl ReplDisplay>;
+    async fn drop(&mut self, opts: &DropOpts) -> anyhow::Result<()>;
+    async fn rename(&mut self, opts: &RenameOpts) -> anyhow::Result<()>;
+    async fn alias(&mut self, opts: &AliasOpts) -> anyhow::Result<()>;
+    async fn refresh(&mut self, opts: &RefreshOpts) -> anyhow::Result<()>;
+    async fn derive(&mut self, opts: &DeriveOpts) -> anyhow::Result<()>;
+    async fn materialize(&mut self, opts: &MaterializeOpts) -> anyhow::Result<()>;
+    async fn list(&self, opts: &ListOpts) -> anyhow::Result<impl ReplDisplay>;
     async fn describe(&self, opts: &DescribeOpts) -> anyhow::Result<impl ReplDisplay>;
     async fn schema(&self, opts: &SchemaOpts) -> anyhow::Result<impl ReplDisplay>;
     async fn head(&self, opts: &HeadOpts) -> anyhow::Result<impl ReplDisplay>;
+    async fn hist(&self, opts: &HistOpts) -> anyhow::Result<impl ReplDisplay>;
+    async fn profile(&self, opts: &ProfileOpts) -> anyhow::Result<impl ReplDisplay>;
+    async fn diff(&self, opts: &DiffOpts) -> anyhow::Result<impl ReplDisplay>;
+    async fn parquet_meta(&self, opts: &ParquetMetaOpts) -> anyhow::Result<impl ReplDisplay>;
     async fn sql(&self, opts: &SqlOpts) -> anyhow::Result<impl ReplDisplay>;
+    async fn explain(&self, opts: &ExplainOpts) -> anyhow::Result<impl ReplDisplay>;
+    async fn functions(&self, opts: &FunctionsOpts) -> anyhow::Result<impl ReplDisplay>;
+    async fn udf(&mut self, opts: &UdfOpts) -> anyhow::Result<String>;
+    async fn define(&mut self, opts: &LetOpts) -> anyhow::Result<String>;
+    async fn variables(&self) -> anyhow::Result<impl ReplDisplay>;
+    async fn prepare(&mut self, opts: &PrepareOpts) -> anyhow::Result<String>;
+    async fn execute(&self, opts: &ExecuteOpts) -> anyhow::Result<impl ReplDisplay>;
 }
+#[derive(Clone)]
 pub struct ReplContext {
     pub tx: mpsc::Sender<ReplMsg>,
 }
@@ -60,7 +106,23 @@ impl Default for ReplContext {
 }
 impl ReplContext {
     pub fn new() -> Self {
-        let mut backend = DataFusionBackend::new();
+        Self::try_new(RuntimeSettings::default()).expect("Failed to create the backend")
+    }
+
+    /// with a memory limit and spill directory given at startup
+    pub fn try_new(settings: RuntimeSettings) -> anyhow::Result<Self> {
+        Self::try_new_with_plugins(settings, vec![])
+    }
+
+    /// with udf plugins registered after the functions shipped with taotie
+    pub fn try_new_with_plugins(
+        settings: RuntimeSettings,
+        plugins: Vec<Box<dyn UdfPlugin>>,
+    ) -> anyhow::Result<Self> {
+        let mut backend = DataFusionBackend::try_new(settings)?;
+        for plugin in plugins.iter() {
+            backend.register_plugin(plugin.as_ref())?;
+        }
         let rt = Runtime::new().expect("Failed to create tokio runtime");
         let (tx, rx) = mpsc::unbounded::<ReplMsg>();
 
@@ -77,12 +139,15 @@ impl ReplContext {
                         msg.tx.send(res).unwrap();
                         Ok::<_, anyhow::Error>(())
                     }) {
-                        println!("Error: {}", e);
+                        println!("Error: {:#}", e);
+                        if is_resources_exhausted(&e) {
+                            println!("the query needs more memory than memory_limit allows even after spilling, raise it with `.set memory_limit <size>`");
+                        }
                     }
                 }
             })
             .unwrap();
-        ReplContext { tx }
+        Ok(ReplContext { tx })
     }
 
     pub fn send(&self, msg: ReplMsg, tx: mpsc::Receiver<String>) -> Option<String> {
@@ -101,15 +166,41 @@ impl ReplContext {
     }
 }
 
+fn is_resources_exhausted(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<DataFusionError>().map(|e| e.find_root()),
+        Some(DataFusionError::ResourcesExhausted(_))
+    )
+}
+
 pub type ReplCallBacks = CallBackMap<ReplContext, reedline_repl_rs::Error>;
 pub fn get_callbacks() -> ReplCallBacks {
     let mut callback = CallBackMap::new();
     callback.insert("connect".to_string(), cli::connect::connect);
     callback.insert("list".to_string(), cli::list::list);
+    callback.insert("drop".to_string(), cli::drop::drop);
+    callback.insert("rename".to_string(), cli::rename::rename);
+    callback.insert("alias".to_string(), cli::alias::alias);
+    callback.insert("refresh".to_string(), cli::refresh::refresh);
+    callback.insert("derive".to_string(), cli::derive::derive);
+    callback.insert("materialize".to_string(), cli::derive::materialize);
     callback.insert("head".to_string(), cli::head::head);
+    callback.insert("hist".to_string(), cli::hist::hist);
     callback.insert("schema".to_string(), cli::schema::schema);
     callback.insert("describe".to_string(), cli::describe::describe);
+    callback.insert("profile".to_string(), cli::profile::profile);
+    callback.insert("diff".to_string(), cli::diff::diff);
+    callback.insert("parquet-meta".to_string(), cli::parquet_meta::parquet_meta);
     callback.insert("sql".to_string(), cli::sql::sql);
+    callback.insert("explain".to_string(), cli::explain::explain);
+    callback.insert("functions".to_string(), cli::functions::functions);
+    callback.insert("udf".to_string(), cli::udf::udf);
+    callback.insert("let".to_string(), cli::params::define);
+    callback.insert("prepare".to_string(), cli::params::prepare);
+    callback.insert("execute".to_string(), cli::params::execute);
+    callback.insert("timing".to_string(), cli::timing::timing);
+    callback.insert("set".to_string(), cli::set::set);
+    callback.insert("show".to_string(), cli::show::show);
     callback.insert("exit".to_string(), quit);
     callback
 }
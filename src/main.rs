@@ -3,20 +3,120 @@
 use std::path::PathBuf;
 
 use anyhow::Result;
-use reedline_repl_rs::Repl;
-use taotie::{cli::ReplCommand, ReplContext};
+use clap::Parser;
+use reedline_repl_rs::{
+    reedline::{
+        default_emacs_keybindings, ColumnarMenu, DefaultCompleter, DefaultHinter, DefaultPrompt,
+        DefaultPromptSegment, Emacs, ExampleHighlighter, FileBackedHistory, KeyCode, KeyModifiers,
+        MenuBuilder, Reedline, ReedlineEvent, ReedlineMenu, Signal,
+    },
+    Repl,
+};
+use taotie::{
+    backend::fusion::{memory::RuntimeSettings, plugin::UdfPlugin},
+    cli::{
+        dot_commands,
+        set::{parse_size, setting_keys},
+        sql::run_sql,
+        sql_keywords, ReplCommand, ReplInput,
+    },
+    ReplContext,
+};
+
+#[derive(Debug, Parser)]
+#[command(about = "Taotie, your dataset exploration REPL")]
+struct Args {
+    #[arg(
+        short,
+        long,
+        value_parser = parse_memory_limit,
+        help = "Memory available to queries, e.g. 2GB, sorts and aggregations spill beyond it"
+    )]
+    memory_limit: Option<usize>,
+    #[arg(long, help = "Directory for spill files, the os temp dir by default")]
+    spill_dir: Option<PathBuf>,
+}
 
 fn main() -> Result<()> {
+    let args = Args::parse();
     let callbacks = taotie::get_callbacks();
-    let ctx = ReplContext::new();
+    let settings = RuntimeSettings {
+        memory_limit: args.memory_limit,
+        spill_dir: args.spill_dir,
+    };
+    let ctx = ReplContext::try_new_with_plugins(settings, plugins())?;
     let path = PathBuf::from("./assets/command.log");
     // let path = dirs::home_dir().expect("expect home dir").join(".taotie_history");
-    let mut repl = Repl::new(ctx)
-        .with_history(path, 1024)
-        .with_banner("Welcome to Taotie, your dataset exploration REPL!")
-        .with_derived::<ReplCommand>(callbacks);
+    // the repl only dispatches dot-commands, bare sql is sent to the backend directly
+    let mut repl = Repl::new(ctx.clone()).with_derived::<ReplCommand>(callbacks);
+    let mut line_editor = build_line_editor(path)?;
+    let prompt = DefaultPrompt::new(
+        DefaultPromptSegment::Basic("taotie".to_string()),
+        DefaultPromptSegment::Empty,
+    );
 
-    repl.run()?;
+    println!("Welcome to Taotie, your dataset exploration REPL!");
+    loop {
+        match line_editor.read_line(&prompt)? {
+            Signal::Success(line) => match ReplInput::parse(&line) {
+                ReplInput::Empty => {}
+                ReplInput::Sql(query) => {
+                    if let Some(res) = run_sql(query, &ctx) {
+                        println!("{}", res);
+                    }
+                }
+                ReplInput::Command(args) => {
+                    if let Err(e) = repl.process_argv(args) {
+                        eprintln!("{}", e);
+                    }
+                }
+            },
+            Signal::CtrlC => {}
+            Signal::CtrlD => break,
+        }
+    }
 
     Ok(())
 }
+
+/// udf plugins compiled into the binary, registered after the functions shipped with taotie,
+/// e.g. `vec![Box::new(MyPlugin)]`
+fn plugins() -> Vec<Box<dyn UdfPlugin>> {
+    vec![]
+}
+
+fn build_line_editor(history: PathBuf) -> Result<Reedline> {
+    let mut words = dot_commands();
+    words.extend(sql_keywords());
+    words.extend(setting_keys());
+
+    let mut keybindings = default_emacs_keybindings();
+    keybindings.add_binding(
+        KeyModifiers::NONE,
+        KeyCode::Tab,
+        ReedlineEvent::Menu("completion_menu".to_string()),
+    );
+    let menu = ColumnarMenu::default().with_name("completion_menu");
+    let history = FileBackedHistory::with_file(1024, history)?;
+
+    let line_editor = Reedline::create()
+        .with_edit_mode(Box::new(Emacs::new(keybindings)))
+        .with_completer(Box::new(DefaultCompleter::new_with_wordlen(
+            words.clone(),
+            2,
+        )))
+        .with_menu(ReedlineMenu::EngineCompleter(Box::new(menu)))
+        .with_highlighter(Box::new(ExampleHighlighter::new(words)))
+        .with_hinter(Box::new(DefaultHinter::default()))
+        .with_quick_completions(true)
+        .with_history(Box::new(history));
+    Ok(line_editor)
+}
+
+fn parse_memory_limit(s: &str) -> std::result::Result<usize, String> {
+    match parse_size(s) {
+        Ok(Some(limit)) => Ok(limit),
+        Ok(None) => Err("omit --memory-limit for no limit".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}